@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS evm_blocks (
+    chain_id BIGINT NOT NULL REFERENCES evm_chains(id),
+    block_number BIGINT NOT NULL,
+    block_hash BYTEA NOT NULL,
+    parent_hash BYTEA,
+    PRIMARY KEY (chain_id, block_number)
+);
+
+CREATE INDEX idx_evm_blocks_chain_block ON evm_blocks(chain_id, block_number DESC);
@@ -0,0 +1 @@
+ALTER TABLE evm_sync_logs ADD COLUMN IF NOT EXISTS start_block_number BIGINT;
@@ -0,0 +1,2 @@
+ALTER TABLE token_transfers ADD COLUMN IF NOT EXISTS transaction_index INTEGER;
+ALTER TABLE token_transfers ADD COLUMN IF NOT EXISTS block_hash BYTEA;
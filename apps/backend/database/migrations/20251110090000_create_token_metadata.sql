@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS token_metadata (
+    chain_id BIGINT NOT NULL REFERENCES evm_chains(id),
+    contract_address VARCHAR(42) NOT NULL,
+    decimals SMALLINT NOT NULL,
+    PRIMARY KEY (chain_id, contract_address)
+);
@@ -0,0 +1,6 @@
+ALTER TABLE token_metadata ALTER COLUMN decimals DROP NOT NULL;
+ALTER TABLE token_metadata ADD COLUMN IF NOT EXISTS name TEXT;
+ALTER TABLE token_metadata ADD COLUMN IF NOT EXISTS symbol TEXT;
+ALTER TABLE token_metadata ADD COLUMN IF NOT EXISTS total_supply DECIMAL(78,0);
+ALTER TABLE token_metadata ADD COLUMN IF NOT EXISTS first_seen_block BIGINT;
+ALTER TABLE token_metadata ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP;
@@ -0,0 +1,24 @@
+-- Scope transfers and sync cursors by chain so the same contract address can be
+-- tracked on several chains.
+ALTER TABLE token_transfers ADD COLUMN IF NOT EXISTS chain_id BIGINT;
+
+UPDATE token_transfers t
+SET chain_id = s.chain_id
+FROM evm_sync_logs s
+WHERE s.contract_address = t.contract_address AND t.chain_id IS NULL;
+
+ALTER TABLE token_transfers ALTER COLUMN chain_id SET NOT NULL;
+
+ALTER TABLE token_transfers DROP CONSTRAINT IF EXISTS token_transfers_contract_address_fkey;
+ALTER TABLE evm_sync_logs DROP CONSTRAINT IF EXISTS evm_sync_logs_pkey;
+ALTER TABLE evm_sync_logs ADD PRIMARY KEY (chain_id, contract_address);
+ALTER TABLE token_transfers
+    ADD CONSTRAINT token_transfers_sync_log_fkey
+    FOREIGN KEY (chain_id, contract_address) REFERENCES evm_sync_logs(chain_id, contract_address);
+
+ALTER TABLE token_transfers DROP CONSTRAINT IF EXISTS token_transfers_transaction_hash_log_index_key;
+ALTER TABLE token_transfers
+    ADD CONSTRAINT token_transfers_chain_transaction_log_key UNIQUE (chain_id, transaction_hash, log_index);
+
+DROP INDEX IF EXISTS idx_token_transfers_contract_block;
+CREATE INDEX idx_token_transfers_chain_contract_block ON token_transfers(chain_id, contract_address, block_number DESC);
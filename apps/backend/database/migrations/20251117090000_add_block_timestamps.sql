@@ -0,0 +1,10 @@
+ALTER TABLE evm_blocks ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMP WITH TIME ZONE;
+ALTER TABLE token_transfers ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMP WITH TIME ZONE;
+
+-- Transfers indexed before this migration take their timestamp from blocks we already know.
+UPDATE token_transfers t
+SET block_timestamp = b.block_timestamp
+FROM evm_blocks b
+WHERE b.chain_id = t.chain_id AND b.block_number = t.block_number AND t.block_timestamp IS NULL;
+
+CREATE INDEX idx_token_transfers_block_timestamp ON token_transfers(block_timestamp DESC);
@@ -0,0 +1 @@
+ALTER TABLE evm_chains ADD COLUMN IF NOT EXISTS ws_url TEXT;
@@ -0,0 +1,2 @@
+-- Blocks this far below the head are treated as final; newer ones are only streamed as pending.
+ALTER TABLE evm_chains ADD COLUMN IF NOT EXISTS confirmations INTEGER;
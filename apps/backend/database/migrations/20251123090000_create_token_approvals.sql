@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS token_approvals (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash BYTEA NOT NULL,
+    block_timestamp TIMESTAMP WITH TIME ZONE,
+    transaction_hash BYTEA NOT NULL,
+    transaction_index INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    owner_address BYTEA NOT NULL,
+    spender_address BYTEA NOT NULL,
+    amount DECIMAL(78,0) NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT token_approvals_chain_transaction_log_key UNIQUE (chain_id, transaction_hash, log_index),
+    CONSTRAINT token_approvals_sync_log_fkey
+        FOREIGN KEY (chain_id, contract_address) REFERENCES evm_sync_logs(chain_id, contract_address)
+);
+
+-- The latest approval of a pair is its current allowance.
+CREATE INDEX idx_token_approvals_pair ON token_approvals(chain_id, contract_address, owner_address, spender_address, block_number DESC, log_index DESC);
+CREATE INDEX idx_token_approvals_spender ON token_approvals(spender_address, chain_id);
+CREATE INDEX idx_token_approvals_chain_block ON token_approvals(chain_id, block_number);
@@ -0,0 +1,28 @@
+-- 'erc20', 'erc721' or 'erc1155', detected through ERC-165 when a contract is first indexed.
+ALTER TABLE evm_sync_logs ADD COLUMN IF NOT EXISTS token_standard VARCHAR(16);
+
+CREATE TABLE IF NOT EXISTS nft_transfers (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash BYTEA NOT NULL,
+    block_timestamp TIMESTAMP WITH TIME ZONE,
+    transaction_hash BYTEA NOT NULL,
+    transaction_index INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    -- Position within an ERC-1155 TransferBatch, 0 for single transfers.
+    batch_index INTEGER NOT NULL DEFAULT 0,
+    operator_address BYTEA,
+    from_address BYTEA NOT NULL,
+    to_address BYTEA NOT NULL,
+    token_id DECIMAL(78,0) NOT NULL,
+    amount DECIMAL(78,0) NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT nft_transfers_chain_transaction_log_key UNIQUE (chain_id, transaction_hash, log_index, batch_index),
+    CONSTRAINT nft_transfers_sync_log_fkey
+        FOREIGN KEY (chain_id, contract_address) REFERENCES evm_sync_logs(chain_id, contract_address)
+);
+
+CREATE INDEX idx_nft_transfers_chain_contract_block ON nft_transfers(chain_id, contract_address, block_number DESC);
+CREATE INDEX idx_nft_transfers_token ON nft_transfers(chain_id, contract_address, token_id);
@@ -0,0 +1,24 @@
+-- Event ABI registered for a contract; matching logs are decoded into contract_events.
+ALTER TABLE evm_sync_logs ADD COLUMN IF NOT EXISTS abi JSONB;
+
+CREATE TABLE IF NOT EXISTS contract_events (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash BYTEA NOT NULL,
+    block_timestamp TIMESTAMP WITH TIME ZONE,
+    transaction_hash BYTEA NOT NULL,
+    transaction_index INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    event_name VARCHAR(128) NOT NULL,
+    event_signature TEXT NOT NULL,
+    params JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT contract_events_chain_transaction_log_key UNIQUE (chain_id, transaction_hash, log_index),
+    CONSTRAINT contract_events_sync_log_fkey
+        FOREIGN KEY (chain_id, contract_address) REFERENCES evm_sync_logs(chain_id, contract_address)
+);
+
+CREATE INDEX idx_contract_events_chain_contract_block ON contract_events(chain_id, contract_address, block_number DESC);
+CREATE INDEX idx_contract_events_name ON contract_events(chain_id, contract_address, event_name);
@@ -0,0 +1,8 @@
+-- One of transfer, mint, burn, wrap or unwrap; see `TransferKind` in the indexer.
+ALTER TABLE token_transfers ADD COLUMN IF NOT EXISTS kind VARCHAR(16) NOT NULL DEFAULT 'transfer';
+
+-- Transfers indexed before this migration are classified by their endpoints.
+UPDATE token_transfers SET kind = 'mint' WHERE from_address = '\x0000000000000000000000000000000000000000'::BYTEA;
+UPDATE token_transfers SET kind = 'burn' WHERE to_address = '\x0000000000000000000000000000000000000000'::BYTEA AND kind = 'transfer';
+
+CREATE INDEX idx_token_transfers_supply ON token_transfers(chain_id, contract_address, block_timestamp) WHERE kind <> 'transfer';
@@ -0,0 +1,31 @@
+-- Balance of a holder after every block in which it changed; the latest row at or
+-- below a block is the balance at that block.
+CREATE TABLE IF NOT EXISTS token_balances (
+    chain_id BIGINT NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    holder_address BYTEA NOT NULL,
+    block_number BIGINT NOT NULL,
+    balance DECIMAL(78,0) NOT NULL,
+    PRIMARY KEY (chain_id, contract_address, holder_address, block_number),
+    CONSTRAINT token_balances_sync_log_fkey
+        FOREIGN KEY (chain_id, contract_address) REFERENCES evm_sync_logs(chain_id, contract_address)
+);
+
+CREATE INDEX idx_token_balances_chain_block ON token_balances(chain_id, block_number);
+
+-- Rebuild the ledger from transfers indexed before this migration.
+INSERT INTO token_balances (chain_id, contract_address, holder_address, block_number, balance)
+SELECT chain_id, contract_address, holder_address, block_number,
+       SUM(delta) OVER (PARTITION BY chain_id, contract_address, holder_address ORDER BY block_number)
+FROM (
+    SELECT chain_id, contract_address, holder_address, block_number, SUM(delta) AS delta
+    FROM (
+        SELECT chain_id, contract_address, to_address AS holder_address, block_number, amount AS delta
+        FROM token_transfers
+        UNION ALL
+        SELECT chain_id, contract_address, from_address, block_number, -amount
+        FROM token_transfers
+    ) movements
+    WHERE holder_address <> '\x0000000000000000000000000000000000000000'::BYTEA
+    GROUP BY chain_id, contract_address, holder_address, block_number
+) changes;
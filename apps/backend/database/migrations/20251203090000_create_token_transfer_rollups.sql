@@ -0,0 +1,45 @@
+-- Per-token transfer statistics by UTC hour and day, maintained by the indexer so charts
+-- never scan `token_transfers`. `granularity` is a `date_trunc` field: 'hour' or 'day'.
+CREATE TABLE IF NOT EXISTS token_transfer_rollups (
+    chain_id BIGINT NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    granularity VARCHAR(8) NOT NULL,
+    bucket TIMESTAMP WITH TIME ZONE NOT NULL,
+    transfer_count BIGINT NOT NULL,
+    volume DECIMAL(78,0) NOT NULL,
+    unique_senders BIGINT NOT NULL,
+    unique_receivers BIGINT NOT NULL,
+    PRIMARY KEY (chain_id, contract_address, granularity, bucket),
+    CONSTRAINT token_transfer_rollups_sync_log_fkey
+        FOREIGN KEY (chain_id, contract_address) REFERENCES evm_sync_logs(chain_id, contract_address)
+);
+
+-- Addresses already counted in a bucket, so the unique counts can be kept incrementally.
+CREATE TABLE IF NOT EXISTS token_rollup_participants (
+    chain_id BIGINT NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    granularity VARCHAR(8) NOT NULL,
+    bucket TIMESTAMP WITH TIME ZONE NOT NULL,
+    role VARCHAR(8) NOT NULL,
+    address BYTEA NOT NULL,
+    PRIMARY KEY (chain_id, contract_address, granularity, bucket, role, address)
+);
+
+CREATE INDEX idx_token_transfer_rollups_chain_bucket ON token_transfer_rollups(chain_id, bucket);
+CREATE INDEX idx_token_rollup_participants_chain_bucket ON token_rollup_participants(chain_id, bucket);
+
+-- Build the rollups of transfers indexed before this migration.
+INSERT INTO token_rollup_participants (chain_id, contract_address, granularity, bucket, role, address)
+SELECT DISTINCT t.chain_id, t.contract_address, g.granularity, date_trunc(g.granularity, t.block_timestamp, 'UTC'), p.role, p.address
+FROM token_transfers t
+CROSS JOIN (VALUES ('hour'), ('day')) AS g(granularity)
+CROSS JOIN LATERAL (VALUES ('sender', t.from_address), ('receiver', t.to_address)) AS p(role, address)
+WHERE t.block_timestamp IS NOT NULL;
+
+INSERT INTO token_transfer_rollups (chain_id, contract_address, granularity, bucket, transfer_count, volume, unique_senders, unique_receivers)
+SELECT t.chain_id, t.contract_address, g.granularity, date_trunc(g.granularity, t.block_timestamp, 'UTC'),
+       COUNT(*), SUM(t.amount), COUNT(DISTINCT t.from_address), COUNT(DISTINCT t.to_address)
+FROM token_transfers t
+CROSS JOIN (VALUES ('hour'), ('day')) AS g(granularity)
+WHERE t.block_timestamp IS NOT NULL
+GROUP BY t.chain_id, t.contract_address, g.granularity, date_trunc(g.granularity, t.block_timestamp, 'UTC');
@@ -0,0 +1,16 @@
+-- Rollbacks that removed stored transfers, so a transfer stream resuming from before one
+-- can tell its client to reload. `first_removed_id` is the lowest removed transfer id,
+-- `last_transfer_id` the highest transfer id of the chain before the rollback.
+CREATE TABLE IF NOT EXISTS transfer_rollbacks (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    first_removed_id BIGINT NOT NULL,
+    last_transfer_id BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_transfer_rollbacks_chain ON transfer_rollbacks(chain_id, last_transfer_id);
+
+-- Transfer streams catch up per chain, in id order.
+CREATE INDEX idx_token_transfers_chain_id ON token_transfers(chain_id, id);
@@ -0,0 +1,25 @@
+-- Transfers indexed before 20251110090000 stored amounts divided by 10^decimals, and nothing
+-- tells those rows apart from the raw amounts stored since. Every stored ERC-20 transfer is
+-- dropped, with the balances and rollups summed from them, and each contract is rewound to
+-- just before its first stored transfer so it is indexed again in raw units.
+
+-- Streams resuming from a deleted transfer are told to reload.
+INSERT INTO transfer_rollbacks (chain_id, block_number, first_removed_id, last_transfer_id)
+SELECT chain_id, MIN(block_number) - 1, MIN(id), MAX(id)
+FROM token_transfers
+GROUP BY chain_id;
+
+UPDATE evm_sync_logs s
+SET last_synced_block_number = LEAST(s.last_synced_block_number, first_transfers.block_number - 1)
+FROM (
+    SELECT chain_id, contract_address, MIN(block_number) AS block_number
+    FROM token_transfers
+    GROUP BY chain_id, contract_address
+) first_transfers
+WHERE s.chain_id = first_transfers.chain_id
+  AND s.contract_address = first_transfers.contract_address;
+
+DELETE FROM token_balances;
+DELETE FROM token_rollup_participants;
+DELETE FROM token_transfer_rollups;
+DELETE FROM token_transfers;
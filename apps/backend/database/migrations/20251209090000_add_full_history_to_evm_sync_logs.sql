@@ -0,0 +1,3 @@
+-- Whether indexing started at or before the contract's deployment, so its balance ledger
+-- covers every transfer. Contracts tracked before this was recorded are not known to.
+ALTER TABLE evm_sync_logs ADD COLUMN IF NOT EXISTS full_history BOOLEAN NOT NULL DEFAULT FALSE;
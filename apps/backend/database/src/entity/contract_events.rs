@@ -0,0 +1,114 @@
+use alloy::primitives::Address;
+use sqlx::{Pool, Postgres, postgres::PgConnection, query_as, types::chrono};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ContractEvents {
+    pub id: i64,
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub block_number: i64,
+    pub block_hash: Vec<u8>,
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_hash: Vec<u8>,
+    pub transaction_index: i32,
+    pub log_index: i32,
+    pub event_name: String,
+    pub event_signature: String,
+    pub params: serde_json::Value,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A log decoded with a registered ABI, ready to be inserted into `contract_events`.
+#[derive(Debug, Clone)]
+pub struct NewContractEvent {
+    pub chain_id: u64,
+    pub contract_address: Address,
+    pub block_number: u64,
+    pub block_hash: [u8; 32],
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_hash: [u8; 32],
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub event_name: String,
+    pub event_signature: String,
+    pub params: serde_json::Value,
+}
+
+impl ContractEvents {
+    /// Inserts an event, returning the stored row or `None` when the log was already indexed.
+    pub async fn create(
+        event: &NewContractEvent,
+        tx: &mut PgConnection,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            ContractEvents,
+            "INSERT INTO contract_events (chain_id, contract_address, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, event_name, event_signature, params) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT (chain_id, transaction_hash, log_index) DO NOTHING RETURNING id, chain_id, contract_address, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, event_name, event_signature, params, created_at",
+            event.chain_id as i64,
+            event.contract_address.to_string(),
+            event.block_number as i64,
+            &event.block_hash[..],
+            event.block_timestamp,
+            &event.transaction_hash[..],
+            event.transaction_index as i32,
+            event.log_index as i32,
+            event.event_name,
+            event.event_signature,
+            event.params
+        )
+        .fetch_optional(tx)
+        .await
+    }
+
+    /// Lists the latest events of a contract, optionally only those named `event_name`.
+    pub async fn find_by_contract_address(
+        chain_id: u64,
+        contract_address: &str,
+        event_name: Option<&str>,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            ContractEvents,
+            "SELECT id, chain_id, contract_address, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, event_name, event_signature, params, created_at FROM contract_events
+             WHERE chain_id = $1 AND contract_address = $2 AND ($3::VARCHAR IS NULL OR event_name = $3)
+             ORDER BY block_number DESC, log_index DESC
+             LIMIT $4",
+            chain_id as i64,
+            contract_address,
+            event_name,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn delete_after_block(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            ContractEvents,
+            "DELETE FROM contract_events WHERE chain_id = $1 AND block_number > $2 RETURNING id, chain_id, contract_address, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, event_name, event_signature, params, created_at",
+            chain_id as i64,
+            block_number as i64
+        )
+        .fetch_all(tx)
+        .await
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            ContractEvents,
+            "DELETE FROM contract_events WHERE chain_id = $1 AND contract_address = $2 RETURNING id, chain_id, contract_address, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, event_name, event_signature, params, created_at",
+            chain_id as i64,
+            contract_address
+        )
+        .fetch_all(tx)
+        .await
+    }
+}
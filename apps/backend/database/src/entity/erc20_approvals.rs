@@ -0,0 +1,151 @@
+use alloy::primitives::Address;
+use bigdecimal::BigDecimal;
+use sqlx::{Pool, Postgres, postgres::PgConnection, query_as, types::chrono};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Erc20Approvals {
+    pub id: i64,
+    pub chain_id: i64,
+    pub block_number: i64,
+    pub block_hash: Vec<u8>,
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_hash: Vec<u8>,
+    pub transaction_index: i32,
+    pub log_index: i32,
+    pub owner_address: Vec<u8>,
+    pub spender_address: Vec<u8>,
+    pub amount: BigDecimal,
+    pub contract_address: String,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A decoded `Approval` log, ready to be inserted into `token_approvals`.
+#[derive(Debug, Clone)]
+pub struct NewErc20Approval {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub block_hash: [u8; 32],
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_hash: [u8; 32],
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub owner_address: Vec<u8>,
+    pub spender_address: Vec<u8>,
+    pub amount: alloy::primitives::U256,
+    pub contract_address: Address,
+}
+
+impl Erc20Approvals {
+    /// Inserts an approval, returning the stored row or `None` when the log was
+    /// already indexed.
+    pub async fn create(
+        approval: &NewErc20Approval,
+        tx: &mut PgConnection,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        use std::str::FromStr;
+
+        let amount_decimal = BigDecimal::from_str(&approval.amount.to_string())
+            .map_err(|_| sqlx::Error::Decode("Invalid amount".into()))?;
+
+        query_as!(
+            Erc20Approvals,
+            "INSERT INTO token_approvals (chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, owner_address, spender_address, amount, contract_address) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT (chain_id, transaction_hash, log_index) DO NOTHING RETURNING id, chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, owner_address, spender_address, amount, contract_address, created_at",
+            approval.chain_id as i64,
+            approval.block_number as i64,
+            &approval.block_hash[..],
+            approval.block_timestamp,
+            &approval.transaction_hash[..],
+            approval.transaction_index as i32,
+            approval.log_index as i32,
+            &approval.owner_address,
+            &approval.spender_address,
+            amount_decimal,
+            approval.contract_address.to_string()
+        )
+        .fetch_optional(tx)
+        .await
+    }
+
+    /// The latest approval `owner` gave `spender` on a token. Its amount is what was last
+    /// approved; `transferFrom` spends since then are not subtracted.
+    pub async fn find_latest(
+        chain_id: u64,
+        contract_address: &str,
+        owner_address: &[u8],
+        spender_address: &[u8],
+        pool: &Pool<Postgres>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            Erc20Approvals,
+            "SELECT id, chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, owner_address, spender_address, amount, contract_address, created_at FROM token_approvals
+             WHERE chain_id = $1 AND contract_address = $2 AND owner_address = $3 AND spender_address = $4
+             ORDER BY block_number DESC, log_index DESC
+             LIMIT 1",
+            chain_id as i64,
+            contract_address,
+            owner_address,
+            spender_address
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Lists the pairs whose last approved amount for `spender` is at least `min_amount`,
+    /// on `chain_id` or on every chain.
+    pub async fn find_last_approved_by_spender(
+        chain_id: Option<u64>,
+        spender_address: &[u8],
+        min_amount: &BigDecimal,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            Erc20Approvals,
+            r#"SELECT id AS "id!", chain_id AS "chain_id!", block_number AS "block_number!", block_hash AS "block_hash!", block_timestamp, transaction_hash AS "transaction_hash!", transaction_index AS "transaction_index!", log_index AS "log_index!", owner_address AS "owner_address!", spender_address AS "spender_address!", amount AS "amount!", contract_address AS "contract_address!", created_at FROM (
+                 SELECT DISTINCT ON (chain_id, contract_address, owner_address) *
+                 FROM token_approvals
+                 WHERE spender_address = $1 AND ($2::BIGINT IS NULL OR chain_id = $2)
+                 ORDER BY chain_id, contract_address, owner_address, block_number DESC, log_index DESC
+             ) current
+             WHERE amount >= $3
+             ORDER BY block_number DESC
+             LIMIT $4"#,
+            spender_address,
+            chain_id.map(|chain_id| chain_id as i64),
+            min_amount,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn delete_after_block(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM token_approvals WHERE chain_id = $1 AND block_number > $2",
+            chain_id as i64,
+            block_number as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM token_approvals WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -1,10 +1,11 @@
 use alloy::primitives::Address;
 use bigdecimal::BigDecimal;
-use sqlx::{Pool, Postgres, postgres::PgConnection, query_as, types::chrono};
+use sqlx::{Pool, Postgres, QueryBuilder, postgres::PgConnection, query_as, types::chrono};
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct Erc20Transfers {
     pub id: i64,
+    pub chain_id: i64,
     pub block_number: i64,
     pub transaction_hash: Vec<u8>,
     pub log_index: i32,
@@ -13,56 +14,305 @@ pub struct Erc20Transfers {
     pub amount: BigDecimal,
     pub contract_address: String,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_index: Option<i32>,
+    pub block_hash: Option<Vec<u8>>,
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub kind: String,
+}
+
+/// Filters of `Erc20Transfers::find_filtered`; unset fields match every transfer.
+#[derive(Debug, Clone, Default)]
+pub struct TransferFilter {
+    pub chain_id: Option<u64>,
+    pub contract_address: Option<String>,
+    pub from_address: Option<Vec<u8>>,
+    pub to_address: Option<Vec<u8>>,
+    /// Matches transfers sent or received by this address.
+    pub address: Option<Vec<u8>>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub from_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub to_time: Option<chrono::DateTime<chrono::Utc>>,
+    /// Raw amounts, in the token's smallest unit.
+    pub min_amount: Option<BigDecimal>,
+    pub max_amount: Option<BigDecimal>,
+}
+
+impl TransferFilter {
+    /// Appends one `AND` condition per set field, so only the matching indexes are used.
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(chain_id) = self.chain_id {
+            query.push(" AND chain_id = ").push_bind(chain_id as i64);
+        }
+        if let Some(contract_address) = &self.contract_address {
+            query
+                .push(" AND contract_address = ")
+                .push_bind(contract_address.clone());
+        }
+        if let Some(from_address) = &self.from_address {
+            query
+                .push(" AND from_address = ")
+                .push_bind(from_address.clone());
+        }
+        if let Some(to_address) = &self.to_address {
+            query
+                .push(" AND to_address = ")
+                .push_bind(to_address.clone());
+        }
+        if let Some(address) = &self.address {
+            query
+                .push(" AND (from_address = ")
+                .push_bind(address.clone())
+                .push(" OR to_address = ")
+                .push_bind(address.clone())
+                .push(")");
+        }
+        if let Some(from_block) = self.from_block {
+            query
+                .push(" AND block_number >= ")
+                .push_bind(from_block as i64);
+        }
+        if let Some(to_block) = self.to_block {
+            query
+                .push(" AND block_number <= ")
+                .push_bind(to_block as i64);
+        }
+        if let Some(from_time) = self.from_time {
+            query.push(" AND block_timestamp >= ").push_bind(from_time);
+        }
+        if let Some(to_time) = self.to_time {
+            query.push(" AND block_timestamp <= ").push_bind(to_time);
+        }
+        if let Some(min_amount) = &self.min_amount {
+            query.push(" AND amount >= ").push_bind(min_amount.clone());
+        }
+        if let Some(max_amount) = &self.max_amount {
+            query.push(" AND amount <= ").push_bind(max_amount.clone());
+        }
+    }
+}
+
+/// The last transfer of a page in `Erc20Transfers::find_filtered` order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferCursor {
+    pub block_number: i64,
+    pub log_index: i32,
+    pub id: i64,
+}
+
+/// What an address received and sent of one token.
+#[derive(Debug, sqlx::FromRow)]
+pub struct AddressTokenFlow {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub inbound_count: i64,
+    pub outbound_count: i64,
+    pub received: BigDecimal,
+    pub sent: BigDecimal,
+    pub first_block: i64,
+    pub last_block: i64,
+}
+
+/// Transfers between an address and one counterparty in one token.
+#[derive(Debug, sqlx::FromRow)]
+pub struct Counterparty {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub counterparty_address: Vec<u8>,
+    pub transfer_count: i64,
+    /// Sent by the address to the counterparty.
+    pub sent: BigDecimal,
+    pub received: BigDecimal,
+}
+
+/// Supply created and destroyed in one time bucket, with the running supply at its end.
+#[derive(Debug, sqlx::FromRow)]
+pub struct SupplyPoint {
+    pub bucket: chrono::DateTime<chrono::Utc>,
+    pub minted: BigDecimal,
+    pub burned: BigDecimal,
+    pub supply: BigDecimal,
+}
+
+/// A decoded transfer log, ready to be inserted into `token_transfers`.
+#[derive(Debug, Clone)]
+pub struct NewErc20Transfer {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub block_hash: [u8; 32],
+    pub transaction_hash: [u8; 32],
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub from_address: Vec<u8>,
+    pub to_address: Vec<u8>,
+    pub amount: alloy::primitives::U256,
+    pub contract_address: Address,
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// `transfer`, `mint`, `burn`, `wrap` or `unwrap`.
+    pub kind: String,
 }
 
 impl Erc20Transfers {
+    /// Inserts a transfer, returning the stored row or `None` when the log was
+    /// already indexed.
     pub async fn create(
-        block_number: u64,
-        transaction_hash: [u8; 32],
-        from_address: Vec<u8>,
-        to_address: Vec<u8>,
-        amount: alloy::primitives::U256,
-        contract_address: Address,
+        transfer: &NewErc20Transfer,
         tx: &mut PgConnection,
-    ) -> Result<(), sqlx::Error> {
+    ) -> Result<Option<Self>, sqlx::Error> {
         use std::str::FromStr;
 
-        let amount_decimal = BigDecimal::from_str(&amount.to_string())
+        let amount_decimal = BigDecimal::from_str(&transfer.amount.to_string())
             .map_err(|_| sqlx::Error::Decode("Invalid amount".into()))?;
 
-        sqlx::query(
-            "INSERT INTO token_transfers (block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (transaction_hash, log_index) DO NOTHING"
+        query_as!(
+            Erc20Transfers,
+            "INSERT INTO token_transfers (chain_id, block_number, block_hash, transaction_hash, transaction_index, log_index, from_address, to_address, amount, contract_address, block_timestamp, kind) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) ON CONFLICT (chain_id, transaction_hash, log_index) DO NOTHING RETURNING id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind",
+            transfer.chain_id as i64,
+            transfer.block_number as i64,
+            &transfer.block_hash[..],
+            &transfer.transaction_hash[..],
+            transfer.transaction_index as i32,
+            transfer.log_index as i32,
+            &transfer.from_address,
+            &transfer.to_address,
+            amount_decimal,
+            transfer.contract_address.to_string(),
+            transfer.block_timestamp,
+            transfer.kind
         )
-        .bind(block_number as i64)
-        .bind(&transaction_hash[..])
-        .bind(0i32)
-        .bind(&from_address)
-        .bind(&to_address)
-        .bind(amount_decimal)
-        .bind(contract_address.to_string())
-        .execute(tx)
-        .await?;
-        Ok(())
+        .fetch_optional(tx)
+        .await
     }
 
     pub async fn find_all(limit: i64, pool: &Pool<Postgres>) -> Result<Vec<Self>, sqlx::Error> {
         query_as!(
             Erc20Transfers,
-            "SELECT id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at FROM token_transfers ORDER BY id DESC LIMIT $1",
+            "SELECT id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind FROM token_transfers ORDER BY id DESC LIMIT $1",
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Transfers a subscriber has not seen yet, oldest first: on every chain those after
+    /// its id in `last_ids`, or after `default_id` on chains missing from `chain_ids`.
+    /// The batches of a chain are committed one after another, so its ids only grow.
+    pub async fn find_after_ids(
+        chain_ids: &[i64],
+        last_ids: &[i64],
+        default_id: i64,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            Erc20Transfers,
+            r#"SELECT t.id AS "id!", t.chain_id AS "chain_id!", t.block_number AS "block_number!", t.transaction_hash AS "transaction_hash!", t.log_index AS "log_index!", t.from_address AS "from_address!", t.to_address AS "to_address!", t.amount AS "amount!", t.contract_address AS "contract_address!", t.created_at, t.transaction_index, t.block_hash, t.block_timestamp, t.kind AS "kind!"
+               FROM evm_chains c
+               LEFT JOIN UNNEST($1::BIGINT[], $2::BIGINT[]) AS seen(chain_id, last_id) ON seen.chain_id = c.id
+               CROSS JOIN LATERAL (
+                 SELECT * FROM token_transfers
+                 WHERE chain_id = c.id AND id > COALESCE(seen.last_id, $3)
+                 ORDER BY id LIMIT $4
+               ) t
+               ORDER BY t.id LIMIT $4"#,
+            chain_ids,
+            last_ids,
+            default_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Id of the newest stored transfer of every chain that has one.
+    pub async fn max_ids_by_chain(pool: &Pool<Postgres>) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"SELECT chain_id, MAX(id) AS "max_id!" FROM token_transfers GROUP BY chain_id"#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.chain_id, row.max_id))
+            .collect())
+    }
+
+    /// Id of the newest stored transfer of `chain_id`, `0` when there is none.
+    pub async fn max_id_by_chain(chain_id: u64, tx: &mut PgConnection) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query_scalar!(
+            "SELECT MAX(id) FROM token_transfers WHERE chain_id = $1",
+            chain_id as i64
+        )
+        .fetch_one(tx)
+        .await?;
+        Ok(result.unwrap_or_default())
+    }
+
+    pub async fn find_all_by_chain_id(
+        chain_id: u64,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            Erc20Transfers,
+            "SELECT id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind FROM token_transfers WHERE chain_id = $1 ORDER BY id DESC LIMIT $2",
+            chain_id as i64,
             limit
         )
         .fetch_all(pool)
         .await
     }
 
+    /// Lists transfers matching `filter` in chain order, by block number, then log
+    /// index. With a `cursor`, the page starts right after that transfer.
+    pub async fn find_filtered(
+        filter: &TransferFilter,
+        ascending: bool,
+        cursor: Option<TransferCursor>,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind FROM token_transfers WHERE TRUE",
+        );
+        filter.push_conditions(&mut query);
+
+        let (comparison, direction) = if ascending {
+            (">", "ASC")
+        } else {
+            ("<", "DESC")
+        };
+        if let Some(cursor) = cursor {
+            query
+                .push(" AND (block_number, log_index, id) ")
+                .push(comparison)
+                .push(" (")
+                .push_bind(cursor.block_number)
+                .push(", ")
+                .push_bind(cursor.log_index)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        query
+            .push(format!(
+                " ORDER BY block_number {direction}, log_index {direction}, id {direction} LIMIT "
+            ))
+            .push_bind(limit);
+
+        query.build_query_as::<Self>().fetch_all(pool).await
+    }
+
     pub async fn find_by_contract_address(
+        chain_id: u64,
         contract_address: &str,
         limit: i64,
         pool: &Pool<Postgres>,
     ) -> Result<Vec<Self>, sqlx::Error> {
         query_as!(
             Erc20Transfers,
-            "SELECT id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at FROM token_transfers WHERE contract_address = $1 ORDER BY block_number DESC LIMIT $2",
+            "SELECT id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind FROM token_transfers WHERE chain_id = $1 AND contract_address = $2 ORDER BY block_number DESC LIMIT $3",
+            chain_id as i64,
             contract_address,
             limit
         )
@@ -70,12 +320,45 @@ impl Erc20Transfers {
         .await
     }
 
+    /// Deletes every transfer above `block_number` on `chain_id`, returning the removed rows so they can be retracted downstream.
+    pub async fn delete_after_block(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            Erc20Transfers,
+            "DELETE FROM token_transfers WHERE chain_id = $2 AND block_number > $1 RETURNING id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind",
+            block_number as i64,
+            chain_id as i64
+        )
+        .fetch_all(tx)
+        .await
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            Erc20Transfers,
+            "DELETE FROM token_transfers WHERE chain_id = $1 AND contract_address = $2 RETURNING id, chain_id, block_number, transaction_hash, log_index, from_address, to_address, amount, contract_address, created_at, transaction_index, block_hash, block_timestamp, kind",
+            chain_id as i64,
+            contract_address
+        )
+        .fetch_all(tx)
+        .await
+    }
+
     pub async fn sum_amounts_by_contract_address(
+        chain_id: u64,
         contract_address: &str,
         pool: &Pool<Postgres>,
     ) -> Result<BigDecimal, sqlx::Error> {
         let result: Option<BigDecimal> = sqlx::query_scalar!(
-            "SELECT COALESCE(SUM(amount), 0) FROM token_transfers WHERE contract_address = $1",
+            "SELECT COALESCE(SUM(amount), 0) FROM token_transfers WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
             contract_address
         )
         .fetch_one(pool)
@@ -83,4 +366,106 @@ impl Erc20Transfers {
 
         Ok(result.unwrap_or_else(|| BigDecimal::from(0)))
     }
+
+    /// Mints and wraps minus burns and unwraps of a token, bucketed by UTC `interval`
+    /// (`hour` or `day`) like the rollups. The running supply counts every earlier bucket, so it is only
+    /// the circulating supply when the token was indexed from its deployment.
+    pub async fn supply_over_time(
+        chain_id: u64,
+        contract_address: &str,
+        interval: &str,
+        from_time: Option<chrono::DateTime<chrono::Utc>>,
+        to_time: Option<chrono::DateTime<chrono::Utc>>,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<SupplyPoint>, sqlx::Error> {
+        query_as!(
+            SupplyPoint,
+            r#"SELECT bucket AS "bucket!", minted AS "minted!", burned AS "burned!", supply AS "supply!" FROM (
+                 SELECT bucket, minted, burned, SUM(minted - burned) OVER (ORDER BY bucket) AS supply
+                 FROM (
+                   SELECT date_trunc($3::TEXT, block_timestamp, 'UTC') AS bucket,
+                          COALESCE(SUM(amount) FILTER (WHERE kind IN ('mint', 'wrap')), 0) AS minted,
+                          COALESCE(SUM(amount) FILTER (WHERE kind IN ('burn', 'unwrap')), 0) AS burned
+                   FROM token_transfers
+                   WHERE chain_id = $1 AND contract_address = $2 AND kind <> 'transfer'
+                     AND block_timestamp IS NOT NULL
+                     AND ($5::TIMESTAMPTZ IS NULL OR block_timestamp <= $5)
+                   GROUP BY 1
+                 ) buckets
+               ) supply
+               WHERE $4::TIMESTAMPTZ IS NULL OR bucket >= date_trunc($3::TEXT, $4::TIMESTAMPTZ, 'UTC')
+               ORDER BY bucket"#,
+            chain_id as i64,
+            contract_address,
+            interval,
+            from_time,
+            to_time
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Inbound and outbound totals of `address` per token, on `chain_id` or every chain.
+    pub async fn flows_by_address(
+        chain_id: Option<u64>,
+        address: &[u8],
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<AddressTokenFlow>, sqlx::Error> {
+        query_as!(
+            AddressTokenFlow,
+            r#"SELECT chain_id, contract_address,
+                      COUNT(*) FILTER (WHERE to_address = $1) AS "inbound_count!",
+                      COUNT(*) FILTER (WHERE from_address = $1) AS "outbound_count!",
+                      COALESCE(SUM(amount) FILTER (WHERE to_address = $1), 0) AS "received!",
+                      COALESCE(SUM(amount) FILTER (WHERE from_address = $1), 0) AS "sent!",
+                      MIN(block_number) AS "first_block!",
+                      MAX(block_number) AS "last_block!"
+               FROM token_transfers
+               WHERE (from_address = $1 OR to_address = $1)
+                 AND ($2::BIGINT IS NULL OR chain_id = $2)
+               GROUP BY chain_id, contract_address
+               ORDER BY chain_id, contract_address"#,
+            address,
+            chain_id.map(|chain_id| chain_id as i64)
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// The `per_token` counterparties of `address` with the largest volume in each token.
+    pub async fn top_counterparties(
+        chain_id: Option<u64>,
+        address: &[u8],
+        per_token: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Counterparty>, sqlx::Error> {
+        query_as!(
+            Counterparty,
+            r#"SELECT chain_id AS "chain_id!", contract_address AS "contract_address!",
+                      counterparty_address AS "counterparty_address!", transfer_count AS "transfer_count!",
+                      sent AS "sent!", received AS "received!"
+               FROM (
+                 SELECT chain_id, contract_address, counterparty_address,
+                        COUNT(*) AS transfer_count,
+                        COALESCE(SUM(amount) FILTER (WHERE from_address = $1), 0) AS sent,
+                        COALESCE(SUM(amount) FILTER (WHERE to_address = $1), 0) AS received,
+                        ROW_NUMBER() OVER (PARTITION BY chain_id, contract_address ORDER BY SUM(amount) DESC, counterparty_address) AS rank
+                 FROM (
+                   SELECT chain_id, contract_address, amount, from_address, to_address,
+                          CASE WHEN from_address = $1 THEN to_address ELSE from_address END AS counterparty_address
+                   FROM token_transfers
+                   WHERE (from_address = $1 OR to_address = $1)
+                     AND ($2::BIGINT IS NULL OR chain_id = $2)
+                 ) transfers
+                 GROUP BY chain_id, contract_address, counterparty_address
+               ) counterparties
+               WHERE rank <= $3
+               ORDER BY chain_id, contract_address, rank"#,
+            address,
+            chain_id.map(|chain_id| chain_id as i64),
+            per_token
+        )
+        .fetch_all(pool)
+        .await
+    }
 }
@@ -0,0 +1,111 @@
+use sqlx::{Pool, Postgres, postgres::PgConnection, query, query_as, types::chrono};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct EvmBlocks {
+    pub chain_id: i64,
+    pub block_number: i64,
+    pub block_hash: Vec<u8>,
+    pub parent_hash: Option<Vec<u8>>,
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl EvmBlocks {
+    pub async fn upsert(
+        chain_id: u64,
+        block_number: u64,
+        block_hash: &[u8],
+        parent_hash: Option<&[u8]>,
+        block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "INSERT INTO evm_blocks (chain_id, block_number, block_hash, parent_hash, block_timestamp) VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (chain_id, block_number) DO UPDATE SET block_hash = EXCLUDED.block_hash,
+                 parent_hash = CASE WHEN evm_blocks.block_hash = EXCLUDED.block_hash THEN COALESCE(EXCLUDED.parent_hash, evm_blocks.parent_hash) ELSE EXCLUDED.parent_hash END,
+                 block_timestamp = CASE WHEN evm_blocks.block_hash = EXCLUDED.block_hash THEN COALESCE(EXCLUDED.block_timestamp, evm_blocks.block_timestamp) ELSE EXCLUDED.block_timestamp END",
+            chain_id as i64,
+            block_number as i64,
+            block_hash,
+            parent_hash,
+            block_timestamp
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn find_by_number(
+        chain_id: u64,
+        block_number: u64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            EvmBlocks,
+            "SELECT chain_id, block_number, block_hash, parent_hash, block_timestamp FROM evm_blocks WHERE chain_id = $1 AND block_number = $2",
+            chain_id as i64,
+            block_number as i64
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Returns the recorded blocks at or below `block_number`, newest first.
+    pub async fn find_before(
+        chain_id: u64,
+        block_number: u64,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            EvmBlocks,
+            "SELECT chain_id, block_number, block_hash, parent_hash, block_timestamp FROM evm_blocks WHERE chain_id = $1 AND block_number <= $2 ORDER BY block_number DESC LIMIT $3",
+            chain_id as i64,
+            block_number as i64,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn find_in_range(
+        chain_id: u64,
+        from_block: u64,
+        to_block: u64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            EvmBlocks,
+            "SELECT chain_id, block_number, block_hash, parent_hash, block_timestamp FROM evm_blocks WHERE chain_id = $1 AND block_number BETWEEN $2 AND $3",
+            chain_id as i64,
+            from_block as i64,
+            to_block as i64
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn delete_after(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<u64, sqlx::Error> {
+        let result = query!(
+            "DELETE FROM evm_blocks WHERE chain_id = $1 AND block_number > $2",
+            chain_id as i64,
+            block_number as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_by_chain(chain_id: u64, tx: &mut PgConnection) -> Result<u64, sqlx::Error> {
+        let result = query!(
+            "DELETE FROM evm_blocks WHERE chain_id = $1",
+            chain_id as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
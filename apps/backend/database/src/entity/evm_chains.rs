@@ -1,4 +1,4 @@
-use sqlx::{Pool, Postgres, query_as};
+use sqlx::{Pool, Postgres, postgres::PgConnection, query, query_as};
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct EvmChains {
@@ -6,13 +6,17 @@ pub struct EvmChains {
     pub name: String,
     pub rpc_url: Option<String>,
     pub block_time: Option<i32>,
+    /// WebSocket endpoint used to subscribe to new heads; the chain is polled without it.
+    pub ws_url: Option<String>,
+    /// Blocks a transfer must be buried under before it is stored; `None` stores head blocks.
+    pub confirmations: Option<i32>,
 }
 
 impl EvmChains {
     pub async fn fetch_by_id(id: u64, pool: &Pool<Postgres>) -> Result<Self, sqlx::Error> {
         query_as!(
             EvmChains,
-            "SELECT id, name, rpc_url, block_time FROM evm_chains WHERE id = $1",
+            "SELECT id, name, rpc_url, block_time, ws_url, confirmations FROM evm_chains WHERE id = $1",
             id as i64
         )
         .fetch_one(pool)
@@ -22,7 +26,7 @@ impl EvmChains {
     pub async fn find_all(pool: &Pool<Postgres>) -> Result<Vec<Self>, sqlx::Error> {
         query_as!(
             EvmChains,
-            "SELECT id, name, rpc_url, block_time FROM evm_chains"
+            "SELECT id, name, rpc_url, block_time, ws_url, confirmations FROM evm_chains"
         )
         .fetch_all(pool)
         .await
@@ -37,7 +41,7 @@ impl EvmChains {
     ) -> Result<Self, sqlx::Error> {
         query_as!(
             EvmChains,
-            "INSERT INTO evm_chains (id, name, rpc_url, block_time) VALUES ($1, $2, $3, $4) RETURNING id, name, rpc_url, block_time",
+            "INSERT INTO evm_chains (id, name, rpc_url, block_time) VALUES ($1, $2, $3, $4) RETURNING id, name, rpc_url, block_time, ws_url, confirmations",
             id,
             name,
             rpc_url,
@@ -47,6 +51,25 @@ impl EvmChains {
         .await
     }
 
+    /// Adds a chain unless one with `id` exists; returns whether it was added. Existing
+    /// chains keep their settings.
+    pub async fn create_if_missing(
+        id: u64,
+        name: &str,
+        rpc_url: &str,
+        pool: &Pool<Postgres>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query!(
+            "INSERT INTO evm_chains (id, name, rpc_url) VALUES ($1, $2, $3) ON CONFLICT (id) DO NOTHING",
+            id as i64,
+            name,
+            rpc_url
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn upsert(
         id: i64,
         name: &str,
@@ -58,7 +81,7 @@ impl EvmChains {
             EvmChains,
             "INSERT INTO evm_chains (id, name, rpc_url, block_time) VALUES ($1, $2, $3, $4)
              ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, rpc_url = EXCLUDED.rpc_url, block_time = EXCLUDED.block_time
-             RETURNING id, name, rpc_url, block_time",
+             RETURNING id, name, rpc_url, block_time, ws_url, confirmations",
             id,
             name,
             rpc_url,
@@ -67,4 +90,42 @@ impl EvmChains {
         .fetch_one(pool)
         .await
     }
+
+    pub async fn update_ws_url(
+        id: u64,
+        ws_url: Option<&str>,
+        pool: &Pool<Postgres>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query!(
+            "UPDATE evm_chains SET ws_url = $2 WHERE id = $1",
+            id as i64,
+            ws_url
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_confirmations(
+        id: u64,
+        confirmations: Option<i32>,
+        pool: &Pool<Postgres>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query!(
+            "UPDATE evm_chains SET confirmations = $2 WHERE id = $1",
+            id as i64,
+            confirmations
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes a chain. Its contracts and cached blocks have to be deleted first.
+    pub async fn delete(id: u64, tx: &mut PgConnection) -> Result<bool, sqlx::Error> {
+        let result = query!("DELETE FROM evm_chains WHERE id = $1", id as i64)
+            .execute(tx)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
 }
@@ -5,6 +5,14 @@ pub struct EvmSyncLogs {
     pub contract_address: String,
     pub last_synced_block_number: i64,
     pub chain_id: i64,
+    pub start_block_number: Option<i64>,
+    /// `erc20`, `erc721` or `erc1155`; `None` until detected.
+    pub token_standard: Option<String>,
+    /// JSON ABI of the events to decode into `contract_events`, if registered.
+    pub abi: Option<serde_json::Value>,
+    /// Whether indexing started at or before the contract's deployment; its balances
+    /// only add up when it did.
+    pub full_history: bool,
 }
 
 impl EvmSyncLogs {
@@ -15,7 +23,7 @@ impl EvmSyncLogs {
     ) -> Result<Self, sqlx::Error> {
         let result = query_as!(
             EvmSyncLogs,
-            "SELECT contract_address, last_synced_block_number, chain_id FROM evm_sync_logs WHERE contract_address = $1 AND chain_id = $2",
+            "SELECT contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history FROM evm_sync_logs WHERE contract_address = $1 AND chain_id = $2",
             address,
             chain_id as i64
         )
@@ -27,7 +35,7 @@ impl EvmSyncLogs {
             None => {
                 query_as!(
                     EvmSyncLogs,
-                    "INSERT INTO evm_sync_logs (contract_address, chain_id) VALUES ($1, $2) RETURNING contract_address, last_synced_block_number, chain_id",
+                    "INSERT INTO evm_sync_logs (contract_address, chain_id) VALUES ($1, $2) RETURNING contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history",
                     address,
                     chain_id as i64
                 )
@@ -53,13 +61,93 @@ impl EvmSyncLogs {
         Ok(())
     }
 
+    pub async fn update_token_standard(
+        &self,
+        token_standard: &str,
+        pool: &Pool<Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE evm_sync_logs SET token_standard = $1 WHERE contract_address = $2 AND chain_id = $3",
+            token_standard,
+            self.contract_address,
+            self.chain_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_abi(
+        &self,
+        abi: &serde_json::Value,
+        pool: &Pool<Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE evm_sync_logs SET abi = $1 WHERE contract_address = $2 AND chain_id = $3",
+            abi,
+            self.contract_address,
+            self.chain_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_start_block_number(
+        &self,
+        block_number: u64,
+        pool: &Pool<Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE evm_sync_logs SET start_block_number = $1 WHERE contract_address = $2 AND chain_id = $3",
+            block_number as i64,
+            self.contract_address,
+            self.chain_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_full_history(
+        &self,
+        full_history: bool,
+        pool: &Pool<Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE evm_sync_logs SET full_history = $1 WHERE contract_address = $2 AND chain_id = $3",
+            full_history,
+            self.contract_address,
+            self.chain_id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Moves every cursor on `chain_id` that is past `block_number` back to it.
+    pub async fn rewind_chain(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE evm_sync_logs SET last_synced_block_number = $1 WHERE chain_id = $2 AND last_synced_block_number > $1",
+            block_number as i64,
+            chain_id as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+
     pub async fn find_all_by_chain_id(
         chain_id: u64,
         pool: &Pool<Postgres>,
     ) -> Result<Vec<Self>, sqlx::Error> {
         query_as!(
             EvmSyncLogs,
-            "SELECT contract_address, last_synced_block_number, chain_id FROM evm_sync_logs WHERE chain_id = $1",
+            "SELECT contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history FROM evm_sync_logs WHERE chain_id = $1",
             chain_id as i64
         )
         .fetch_all(pool)
@@ -73,7 +161,7 @@ impl EvmSyncLogs {
     ) -> Result<Self, sqlx::Error> {
         query_as!(
             EvmSyncLogs,
-            "INSERT INTO evm_sync_logs (contract_address, chain_id) VALUES ($1, $2) RETURNING contract_address, last_synced_block_number, chain_id",
+            "INSERT INTO evm_sync_logs (contract_address, chain_id) VALUES ($1, $2) RETURNING contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history",
             contract_address,
             chain_id
         )
@@ -81,9 +169,57 @@ impl EvmSyncLogs {
         .await
     }
 
-    pub async fn find_all_addresses(pool: &Pool<Postgres>) -> Result<Vec<String>, sqlx::Error> {
-        sqlx::query_scalar!("SELECT DISTINCT contract_address FROM evm_sync_logs")
-            .fetch_all(pool)
-            .await
+    pub async fn find_all(pool: &Pool<Postgres>) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            EvmSyncLogs,
+            "SELECT contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history FROM evm_sync_logs ORDER BY chain_id, contract_address"
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn find_all_by_address(
+        contract_address: &str,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            EvmSyncLogs,
+            "SELECT contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history FROM evm_sync_logs WHERE contract_address = $1",
+            contract_address
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// The sync log of a contract, if it is tracked on `chain_id`.
+    pub async fn find_by_address(
+        contract_address: &str,
+        chain_id: u64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            EvmSyncLogs,
+            "SELECT contract_address, last_synced_block_number, chain_id, start_block_number, token_standard, abi, full_history FROM evm_sync_logs WHERE contract_address = $1 AND chain_id = $2",
+            contract_address,
+            chain_id as i64
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Stops tracking a contract. Everything indexed for it has to be deleted first.
+    pub async fn delete(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query!(
+            "DELETE FROM evm_sync_logs WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected() > 0)
     }
 }
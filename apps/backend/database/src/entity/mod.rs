@@ -1,3 +1,11 @@
+pub mod contract_events;
+pub mod erc20_approvals;
 pub mod erc20_transfers;
+pub mod evm_blocks;
 pub mod evm_chains;
 pub mod evm_sync_logs;
+pub mod nft_transfers;
+pub mod token_balances;
+pub mod token_metadata;
+pub mod token_transfer_rollups;
+pub mod transfer_rollbacks;
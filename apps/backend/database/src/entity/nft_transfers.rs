@@ -0,0 +1,134 @@
+use alloy::primitives::{Address, U256};
+use bigdecimal::BigDecimal;
+use sqlx::{Pool, Postgres, postgres::PgConnection, query_as, types::chrono};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct NftTransfers {
+    pub id: i64,
+    pub chain_id: i64,
+    pub block_number: i64,
+    pub block_hash: Vec<u8>,
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_hash: Vec<u8>,
+    pub transaction_index: i32,
+    pub log_index: i32,
+    pub batch_index: i32,
+    pub operator_address: Option<Vec<u8>>,
+    pub from_address: Vec<u8>,
+    pub to_address: Vec<u8>,
+    pub token_id: BigDecimal,
+    pub amount: BigDecimal,
+    pub contract_address: String,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A decoded ERC-721 or ERC-1155 transfer, ready to be inserted into `nft_transfers`.
+#[derive(Debug, Clone)]
+pub struct NewNftTransfer {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub block_hash: [u8; 32],
+    pub block_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub transaction_hash: [u8; 32],
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub batch_index: u64,
+    pub operator_address: Option<Vec<u8>>,
+    pub from_address: Vec<u8>,
+    pub to_address: Vec<u8>,
+    pub token_id: U256,
+    pub amount: U256,
+    pub contract_address: Address,
+}
+
+fn to_decimal(value: &U256) -> Result<BigDecimal, sqlx::Error> {
+    use std::str::FromStr;
+
+    BigDecimal::from_str(&value.to_string())
+        .map_err(|_| sqlx::Error::Decode("Invalid amount".into()))
+}
+
+impl NftTransfers {
+    /// Inserts a transfer, returning the stored row or `None` when it was already indexed.
+    pub async fn create(
+        transfer: &NewNftTransfer,
+        tx: &mut PgConnection,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            NftTransfers,
+            "INSERT INTO nft_transfers (chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, batch_index, operator_address, from_address, to_address, token_id, amount, contract_address) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14) ON CONFLICT (chain_id, transaction_hash, log_index, batch_index) DO NOTHING RETURNING id, chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, batch_index, operator_address, from_address, to_address, token_id, amount, contract_address, created_at",
+            transfer.chain_id as i64,
+            transfer.block_number as i64,
+            &transfer.block_hash[..],
+            transfer.block_timestamp,
+            &transfer.transaction_hash[..],
+            transfer.transaction_index as i32,
+            transfer.log_index as i32,
+            transfer.batch_index as i32,
+            transfer.operator_address.as_deref(),
+            &transfer.from_address,
+            &transfer.to_address,
+            to_decimal(&transfer.token_id)?,
+            to_decimal(&transfer.amount)?,
+            transfer.contract_address.to_string()
+        )
+        .fetch_optional(tx)
+        .await
+    }
+
+    /// Lists the latest transfers on `chain_id` (or every chain), optionally of one
+    /// collection and one token id.
+    pub async fn find_recent(
+        chain_id: Option<u64>,
+        contract_address: Option<&str>,
+        token_id: Option<&BigDecimal>,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            NftTransfers,
+            "SELECT id, chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, batch_index, operator_address, from_address, to_address, token_id, amount, contract_address, created_at FROM nft_transfers
+             WHERE ($1::BIGINT IS NULL OR chain_id = $1)
+               AND ($2::VARCHAR IS NULL OR contract_address = $2)
+               AND ($3::DECIMAL IS NULL OR token_id = $3)
+             ORDER BY block_number DESC, log_index DESC, batch_index DESC
+             LIMIT $4",
+            chain_id.map(|chain_id| chain_id as i64),
+            contract_address,
+            token_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn delete_after_block(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            NftTransfers,
+            "DELETE FROM nft_transfers WHERE chain_id = $1 AND block_number > $2 RETURNING id, chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, batch_index, operator_address, from_address, to_address, token_id, amount, contract_address, created_at",
+            chain_id as i64,
+            block_number as i64
+        )
+        .fetch_all(tx)
+        .await
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            NftTransfers,
+            "DELETE FROM nft_transfers WHERE chain_id = $1 AND contract_address = $2 RETURNING id, chain_id, block_number, block_hash, block_timestamp, transaction_hash, transaction_index, log_index, batch_index, operator_address, from_address, to_address, token_id, amount, contract_address, created_at",
+            chain_id as i64,
+            contract_address
+        )
+        .fetch_all(tx)
+        .await
+    }
+}
@@ -0,0 +1,215 @@
+use bigdecimal::BigDecimal;
+use sqlx::{Pool, Postgres, postgres::PgConnection, query_as};
+
+/// Balance of `holder_address` after `block_number`, reconstructed from transfers.
+#[derive(Debug, sqlx::FromRow)]
+pub struct TokenBalances {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub holder_address: Vec<u8>,
+    pub block_number: i64,
+    pub balance: BigDecimal,
+}
+
+/// A holder with a positive balance and its share of all balances.
+#[derive(Debug, sqlx::FromRow)]
+pub struct TokenHolder {
+    pub holder_address: Vec<u8>,
+    pub balance: BigDecimal,
+    pub share: f64,
+}
+
+/// How concentrated the balances of a token are. The ratios are `None` when nothing is held.
+#[derive(Debug, sqlx::FromRow)]
+pub struct HolderDistribution {
+    pub holder_count: i64,
+    pub total_balance: BigDecimal,
+    /// Share of all balances held by the ten largest holders.
+    pub top_10_share: Option<f64>,
+    /// 0 when every holder holds the same, approaching 1 when one holds everything.
+    pub gini: Option<f64>,
+    /// Sum of squared shares; 1 for a single holder, `1 / n` for `n` equal ones.
+    pub herfindahl: Option<f64>,
+}
+
+impl TokenBalances {
+    /// Adds `delta` to the holder's balance as of `block_number`. Blocks have to be
+    /// applied in order, as later rows are not adjusted.
+    pub async fn apply_delta(
+        chain_id: u64,
+        contract_address: &str,
+        holder_address: &[u8],
+        block_number: u64,
+        delta: &BigDecimal,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO token_balances (chain_id, contract_address, holder_address, block_number, balance)
+             VALUES ($1, $2::VARCHAR, $3, $4, COALESCE((
+                 SELECT balance FROM token_balances
+                 WHERE chain_id = $1 AND contract_address = $2::VARCHAR AND holder_address = $3 AND block_number <= $4
+                 ORDER BY block_number DESC LIMIT 1
+             ), 0) + $5)
+             ON CONFLICT (chain_id, contract_address, holder_address, block_number)
+             DO UPDATE SET balance = token_balances.balance + $5",
+            chain_id as i64,
+            contract_address,
+            holder_address,
+            block_number as i64,
+            delta
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+
+    /// The holder's balance at `block_number`, or its latest balance; `None` when it
+    /// never held the token.
+    pub async fn find_at_block(
+        chain_id: u64,
+        contract_address: &str,
+        holder_address: &[u8],
+        block_number: Option<u64>,
+        pool: &Pool<Postgres>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            TokenBalances,
+            "SELECT chain_id, contract_address, holder_address, block_number, balance FROM token_balances
+             WHERE chain_id = $1 AND contract_address = $2 AND holder_address = $3
+               AND ($4::BIGINT IS NULL OR block_number <= $4)
+             ORDER BY block_number DESC
+             LIMIT 1",
+            chain_id as i64,
+            contract_address,
+            holder_address,
+            block_number.map(|block_number| block_number as i64)
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Balances at `block_number` of up to `limit` random holders, for spot checks.
+    pub async fn sample_at_block(
+        chain_id: u64,
+        contract_address: &str,
+        block_number: u64,
+        limit: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            TokenBalances,
+            r#"SELECT chain_id AS "chain_id!", contract_address AS "contract_address!", holder_address AS "holder_address!", block_number AS "block_number!", balance AS "balance!" FROM (
+                 SELECT DISTINCT ON (holder_address) chain_id, contract_address, holder_address, block_number, balance
+                 FROM token_balances
+                 WHERE chain_id = $1 AND contract_address = $2 AND block_number <= $3
+                 ORDER BY holder_address, block_number DESC
+               ) balances
+               ORDER BY random()
+               LIMIT $4"#,
+            chain_id as i64,
+            contract_address,
+            block_number as i64,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn delete_after_block(
+        chain_id: u64,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM token_balances WHERE chain_id = $1 AND block_number > $2",
+            chain_id as i64,
+            block_number as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM token_balances WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Holders with a positive balance at `block_number` (or now), largest first.
+    pub async fn find_holders_at_block(
+        chain_id: u64,
+        contract_address: &str,
+        block_number: Option<u64>,
+        limit: i64,
+        offset: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<TokenHolder>, sqlx::Error> {
+        query_as!(
+            TokenHolder,
+            r#"SELECT holder_address AS "holder_address!", balance AS "balance!", (balance / SUM(balance) OVER ())::FLOAT8 AS "share!" FROM (
+                 SELECT DISTINCT ON (holder_address) holder_address, balance
+                 FROM token_balances
+                 WHERE chain_id = $1 AND contract_address = $2
+                   AND ($3::BIGINT IS NULL OR block_number <= $3)
+                 ORDER BY holder_address, block_number DESC
+               ) balances
+               WHERE balance > 0
+               ORDER BY balance DESC, holder_address
+               LIMIT $4 OFFSET $5"#,
+            chain_id as i64,
+            contract_address,
+            block_number.map(|block_number| block_number as i64),
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Holder count and concentration of the positive balances at `block_number` (or now).
+    pub async fn distribution_at_block(
+        chain_id: u64,
+        contract_address: &str,
+        block_number: Option<u64>,
+        pool: &Pool<Postgres>,
+    ) -> Result<HolderDistribution, sqlx::Error> {
+        // With balances x_1 <= ... <= x_n: Gini = 2 * sum(i * x_i) / (n * sum(x)) - (n + 1) / n.
+        query_as!(
+            HolderDistribution,
+            r#"SELECT COUNT(*) AS "holder_count!",
+                      COALESCE(SUM(balance), 0) AS "total_balance!",
+                      (SUM(balance) FILTER (WHERE rank_desc <= 10) / NULLIF(SUM(balance), 0))::FLOAT8 AS top_10_share,
+                      (2 * SUM(rank_asc * balance) / NULLIF(COUNT(*) * SUM(balance), 0)
+                        - (COUNT(*) + 1)::NUMERIC / NULLIF(COUNT(*), 0))::FLOAT8 AS gini,
+                      (SUM(balance * balance) / NULLIF(SUM(balance) * SUM(balance), 0))::FLOAT8 AS herfindahl
+               FROM (
+                 SELECT balance,
+                        ROW_NUMBER() OVER (ORDER BY balance ASC, holder_address) AS rank_asc,
+                        ROW_NUMBER() OVER (ORDER BY balance DESC, holder_address) AS rank_desc
+                 FROM (
+                   SELECT DISTINCT ON (holder_address) holder_address, balance
+                   FROM token_balances
+                   WHERE chain_id = $1 AND contract_address = $2
+                     AND ($3::BIGINT IS NULL OR block_number <= $3)
+                   ORDER BY holder_address, block_number DESC
+                 ) balances
+                 WHERE balance > 0
+               ) ranked"#,
+            chain_id as i64,
+            contract_address,
+            block_number.map(|block_number| block_number as i64)
+        )
+        .fetch_one(pool)
+        .await
+    }
+}
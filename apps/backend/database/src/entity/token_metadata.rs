@@ -0,0 +1,132 @@
+use bigdecimal::BigDecimal;
+use sqlx::{Pool, Postgres, postgres::PgConnection, query, query_as, types::chrono};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct TokenMetadata {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<i16>,
+    pub total_supply: Option<BigDecimal>,
+    pub first_seen_block: Option<i64>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Token details read from the contract. Fields are `None` when the call reverted.
+#[derive(Debug, Clone, Default)]
+pub struct NewTokenMetadata {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub total_supply: Option<alloy::primitives::U256>,
+}
+
+impl TokenMetadata {
+    pub async fn find_by_address(
+        chain_id: u64,
+        contract_address: &str,
+        pool: &Pool<Postgres>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as!(
+            TokenMetadata,
+            "SELECT chain_id, contract_address, name, symbol, decimals, total_supply, first_seen_block, updated_at FROM token_metadata WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn find_all(pool: &Pool<Postgres>) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            TokenMetadata,
+            "SELECT chain_id, contract_address, name, symbol, decimals, total_supply, first_seen_block, updated_at FROM token_metadata"
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Metadata of the given tokens, paired up by position in `chain_ids` and
+    /// `contract_addresses`. Tokens without stored metadata are left out.
+    pub async fn find_by_contracts(
+        chain_ids: &[i64],
+        contract_addresses: &[String],
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            TokenMetadata,
+            "SELECT chain_id, contract_address, name, symbol, decimals, total_supply, first_seen_block, updated_at FROM token_metadata
+             WHERE (chain_id, contract_address) IN (SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[]))",
+            chain_ids,
+            contract_addresses
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Stores freshly read metadata, keeping the `first_seen_block` already recorded.
+    pub async fn upsert(
+        chain_id: u64,
+        contract_address: &str,
+        metadata: &NewTokenMetadata,
+        pool: &Pool<Postgres>,
+    ) -> Result<Self, sqlx::Error> {
+        use std::str::FromStr;
+
+        let total_supply = metadata
+            .total_supply
+            .map(|total_supply| BigDecimal::from_str(&total_supply.to_string()))
+            .transpose()
+            .map_err(|_| sqlx::Error::Decode("Invalid total supply".into()))?;
+
+        query_as!(
+            TokenMetadata,
+            "INSERT INTO token_metadata (chain_id, contract_address, name, symbol, decimals, total_supply) VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (chain_id, contract_address) DO UPDATE SET name = EXCLUDED.name, symbol = EXCLUDED.symbol, decimals = EXCLUDED.decimals, total_supply = EXCLUDED.total_supply, updated_at = CURRENT_TIMESTAMP
+             RETURNING chain_id, contract_address, name, symbol, decimals, total_supply, first_seen_block, updated_at",
+            chain_id as i64,
+            contract_address,
+            metadata.name,
+            metadata.symbol,
+            metadata.decimals.map(i16::from),
+            total_supply
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Records `block_number` as the first block the token was seen in, unless an
+    /// earlier one is already known.
+    pub async fn update_first_seen_block(
+        chain_id: u64,
+        contract_address: &str,
+        block_number: u64,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "UPDATE token_metadata SET first_seen_block = $3 WHERE chain_id = $1 AND contract_address = $2 AND (first_seen_block IS NULL OR first_seen_block > $3)",
+            chain_id as i64,
+            contract_address,
+            block_number as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM token_metadata WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .execute(tx)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,170 @@
+use bigdecimal::BigDecimal;
+use sqlx::{Pool, Postgres, postgres::PgConnection, query_as, types::chrono};
+
+use super::erc20_transfers::Erc20Transfers;
+
+/// Bucket sizes the rollups are kept in, as `date_trunc` fields.
+pub const GRANULARITIES: [&str; 2] = ["hour", "day"];
+
+/// Transfer statistics of one token in one UTC hour or day.
+#[derive(Debug, sqlx::FromRow)]
+pub struct TokenTransferRollups {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub granularity: String,
+    pub bucket: chrono::DateTime<chrono::Utc>,
+    pub transfer_count: i64,
+    pub volume: BigDecimal,
+    pub unique_senders: i64,
+    pub unique_receivers: i64,
+}
+
+impl TokenTransferRollups {
+    /// Adds a newly stored transfer to its hourly and daily buckets. Transfers without
+    /// a block timestamp belong to no bucket and are skipped.
+    pub async fn record(
+        transfer: &Erc20Transfers,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        let Some(block_timestamp) = transfer.block_timestamp else {
+            return Ok(());
+        };
+
+        for granularity in GRANULARITIES {
+            sqlx::query!(
+                "WITH sender AS (
+                     INSERT INTO token_rollup_participants (chain_id, contract_address, granularity, bucket, role, address)
+                     VALUES ($1, $2, $3::TEXT, date_trunc($3::TEXT, $4::TIMESTAMPTZ, 'UTC'), 'sender', $5)
+                     ON CONFLICT DO NOTHING RETURNING 1
+                 ), receiver AS (
+                     INSERT INTO token_rollup_participants (chain_id, contract_address, granularity, bucket, role, address)
+                     VALUES ($1, $2, $3::TEXT, date_trunc($3::TEXT, $4::TIMESTAMPTZ, 'UTC'), 'receiver', $6)
+                     ON CONFLICT DO NOTHING RETURNING 1
+                 )
+                 INSERT INTO token_transfer_rollups (chain_id, contract_address, granularity, bucket, transfer_count, volume, unique_senders, unique_receivers)
+                 VALUES ($1, $2, $3::TEXT, date_trunc($3::TEXT, $4::TIMESTAMPTZ, 'UTC'), 1, $7, (SELECT COUNT(*) FROM sender), (SELECT COUNT(*) FROM receiver))
+                 ON CONFLICT (chain_id, contract_address, granularity, bucket) DO UPDATE SET
+                     transfer_count = token_transfer_rollups.transfer_count + 1,
+                     volume = token_transfer_rollups.volume + EXCLUDED.volume,
+                     unique_senders = token_transfer_rollups.unique_senders + EXCLUDED.unique_senders,
+                     unique_receivers = token_transfer_rollups.unique_receivers + EXCLUDED.unique_receivers",
+                transfer.chain_id,
+                transfer.contract_address,
+                granularity,
+                block_timestamp,
+                transfer.from_address,
+                transfer.to_address,
+                transfer.amount
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Recomputes every bucket of `chain_id` from `since` on out of `token_transfers`,
+    /// after transfers in those buckets were deleted.
+    pub async fn rebuild_since(
+        chain_id: u64,
+        since: chrono::DateTime<chrono::Utc>,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        for granularity in GRANULARITIES {
+            sqlx::query!(
+                "DELETE FROM token_rollup_participants WHERE chain_id = $1 AND granularity = $2 AND bucket >= date_trunc($2::TEXT, $3::TIMESTAMPTZ, 'UTC')",
+                chain_id as i64,
+                granularity,
+                since
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                "DELETE FROM token_transfer_rollups WHERE chain_id = $1 AND granularity = $2 AND bucket >= date_trunc($2::TEXT, $3::TIMESTAMPTZ, 'UTC')",
+                chain_id as i64,
+                granularity,
+                since
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                "INSERT INTO token_rollup_participants (chain_id, contract_address, granularity, bucket, role, address)
+                 SELECT DISTINCT t.chain_id, t.contract_address, $2::TEXT, date_trunc($2::TEXT, t.block_timestamp, 'UTC'), p.role, p.address
+                 FROM token_transfers t
+                 CROSS JOIN LATERAL (VALUES ('sender', t.from_address), ('receiver', t.to_address)) AS p(role, address)
+                 WHERE t.chain_id = $1 AND t.block_timestamp >= date_trunc($2::TEXT, $3::TIMESTAMPTZ, 'UTC')",
+                chain_id as i64,
+                granularity,
+                since
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                "INSERT INTO token_transfer_rollups (chain_id, contract_address, granularity, bucket, transfer_count, volume, unique_senders, unique_receivers)
+                 SELECT chain_id, contract_address, $2::TEXT, date_trunc($2::TEXT, block_timestamp, 'UTC'),
+                        COUNT(*), SUM(amount), COUNT(DISTINCT from_address), COUNT(DISTINCT to_address)
+                 FROM token_transfers
+                 WHERE chain_id = $1 AND block_timestamp >= date_trunc($2::TEXT, $3::TIMESTAMPTZ, 'UTC')
+                 GROUP BY chain_id, contract_address, date_trunc($2::TEXT, block_timestamp, 'UTC')",
+                chain_id as i64,
+                granularity,
+                since
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_by_contract(
+        chain_id: u64,
+        contract_address: &str,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM token_rollup_participants WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM token_transfer_rollups WHERE chain_id = $1 AND contract_address = $2",
+            chain_id as i64,
+            contract_address
+        )
+        .execute(&mut *tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Buckets of a token between `from_time` and `to_time`, oldest first. Buckets
+    /// without transfers have no row.
+    pub async fn find_range(
+        chain_id: u64,
+        contract_address: &str,
+        granularity: &str,
+        from_time: Option<chrono::DateTime<chrono::Utc>>,
+        to_time: Option<chrono::DateTime<chrono::Utc>>,
+        pool: &Pool<Postgres>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as!(
+            TokenTransferRollups,
+            "SELECT chain_id, contract_address, granularity, bucket, transfer_count, volume, unique_senders, unique_receivers FROM token_transfer_rollups
+             WHERE chain_id = $1 AND contract_address = $2 AND granularity = $3
+               AND ($4::TIMESTAMPTZ IS NULL OR bucket >= date_trunc($3, $4::TIMESTAMPTZ, 'UTC'))
+               AND ($5::TIMESTAMPTZ IS NULL OR bucket <= $5)
+             ORDER BY bucket",
+            chain_id as i64,
+            contract_address,
+            granularity,
+            from_time,
+            to_time
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
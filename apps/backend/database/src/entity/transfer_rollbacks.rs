@@ -0,0 +1,63 @@
+use sqlx::{Pool, Postgres, postgres::PgConnection, query, query_scalar, types::chrono};
+
+/// A rollback that removed stored transfers of a chain, after a reorganization or when
+/// a contract stopped being tracked.
+#[derive(Debug, sqlx::FromRow)]
+pub struct TransferRollbacks {
+    pub id: i64,
+    pub chain_id: i64,
+    /// The common ancestor the chain was rolled back to, or the block before the first
+    /// transfer of a removed contract.
+    pub block_number: i64,
+    pub first_removed_id: i64,
+    pub last_transfer_id: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TransferRollbacks {
+    pub async fn create(
+        chain_id: u64,
+        block_number: u64,
+        first_removed_id: i64,
+        last_transfer_id: i64,
+        tx: &mut PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        query!(
+            "INSERT INTO transfer_rollbacks (chain_id, block_number, first_removed_id, last_transfer_id) VALUES ($1, $2, $3, $4)",
+            chain_id as i64,
+            block_number as i64,
+            first_removed_id,
+            last_transfer_id
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether a subscriber that saw the transfers of `chain_id` up to `transfer_id` may
+    /// hold some that were rolled back afterwards.
+    pub async fn exists_after(
+        chain_id: i64,
+        transfer_id: i64,
+        pool: &Pool<Postgres>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM transfer_rollbacks WHERE chain_id = $1 AND first_removed_id <= $2 AND last_transfer_id >= $2)",
+            chain_id,
+            transfer_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(result.unwrap_or_default())
+    }
+
+    pub async fn delete_by_chain(chain_id: u64, tx: &mut PgConnection) -> Result<(), sqlx::Error> {
+        query!(
+            "DELETE FROM transfer_rollbacks WHERE chain_id = $1",
+            chain_id as i64
+        )
+        .execute(tx)
+        .await?;
+        Ok(())
+    }
+}
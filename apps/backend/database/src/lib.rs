@@ -35,10 +35,7 @@ impl Database {
             .parse::<u32>()
             .unwrap();
 
-        let pool = create_pool(db_max_connections).await.unwrap();
-
-        // Seed the database with initial data
-        seed_database(&pool).await?;
+        let pool = create_pool(db_max_connections).await?;
 
         Ok(Database { pool })
     }
@@ -54,28 +51,7 @@ pub async fn initialize_database() -> Result<Pool<Postgres>, sqlx::Error> {
         .parse::<u32>()
         .unwrap();
 
-    let pool = create_pool(db_max_connections).await.unwrap();
-
-    seed_database(&pool).await?;
-
-    Ok(pool)
-}
-
-async fn seed_database(pool: &Pool<Postgres>) -> Result<(), sqlx::Error> {
-    use entity::evm_chains::EvmChains;
-
-    let chains = vec![(
-        1i64,
-        "Ethereum Mainnet",
-        Some("https://eth-mainnet.g.alchemy.com/v2/qqcsONEuBfdAuvm-OclpT"),
-        Some(12),
-    )];
-
-    for (id, name, rpc_url, block_time) in chains {
-        EvmChains::upsert(id, name, rpc_url, block_time, pool).await?;
-    }
-
-    Ok(())
+    create_pool(db_max_connections).await
 }
 
 // psql -U ppp -d indexer_db
@@ -0,0 +1,344 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+
+use database::entity::{
+    contract_events::ContractEvents, erc20_approvals::Erc20Approvals,
+    erc20_transfers::Erc20Transfers, evm_blocks::EvmBlocks, evm_chains::EvmChains,
+    evm_sync_logs::EvmSyncLogs, nft_transfers::NftTransfers, token_balances::TokenBalances,
+    token_metadata::TokenMetadata, token_transfer_rollups::TokenTransferRollups,
+    transfer_rollbacks::TransferRollbacks,
+};
+
+use crate::events::parse_abi;
+use crate::server::{parse_address, AppState, ContractEventResponse, TokenPath, TransferResponse};
+
+/// A request to an `/admin` route carrying `Authorization: Bearer <ADMIN_API_TOKEN>`.
+/// Without a configured token every admin request is refused.
+pub struct Admin;
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = state.admin_token.as_deref().ok_or(StatusCode::FORBIDDEN)?;
+
+        let bearer = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        if constant_time_eq(bearer.trim().as_bytes(), token.as_bytes()) {
+            Ok(Admin)
+        } else {
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Compares without returning at the first differing byte, so response times do not
+/// leak how much of a guessed token is right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Body of `POST /admin/chains`. Posting an existing chain replaces its settings.
+#[derive(Debug, Deserialize)]
+pub struct NewChainRequest {
+    pub id: u64,
+    pub name: String,
+    pub rpc_url: String,
+    /// Seconds between polls; 12 when unset.
+    pub block_time: Option<i32>,
+    /// Subscribes to `newHeads` to index blocks as they are announced.
+    pub ws_url: Option<String>,
+    pub confirmations: Option<i32>,
+}
+
+/// A chain as returned by the admin API; RPC URLs are left out as they often hold keys.
+#[derive(Serialize, Deserialize)]
+pub struct ChainResponse {
+    pub id: i64,
+    pub name: String,
+    pub block_time: Option<i32>,
+    pub confirmations: Option<i32>,
+}
+
+impl From<EvmChains> for ChainResponse {
+    fn from(chain: EvmChains) -> Self {
+        Self {
+            id: chain.id,
+            name: chain.name,
+            block_time: chain.block_time,
+            confirmations: chain.confirmations,
+        }
+    }
+}
+
+/// Body of `POST /admin/contracts`.
+#[derive(Debug, Deserialize)]
+pub struct NewContractRequest {
+    pub chain_id: u64,
+    pub address: String,
+    /// First block to index; the deployment block is detected when unset.
+    pub start_block: Option<u64>,
+    /// Events to decode, as a JSON ABI or a list of event signatures.
+    pub abi: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContractResponse {
+    pub chain_id: i64,
+    pub address: String,
+    pub start_block_number: Option<i64>,
+    pub last_synced_block_number: i64,
+    pub token_standard: Option<String>,
+}
+
+impl From<EvmSyncLogs> for ContractResponse {
+    fn from(sync_log: EvmSyncLogs) -> Self {
+        Self {
+            chain_id: sync_log.chain_id,
+            address: sync_log.contract_address,
+            start_block_number: sync_log.start_block_number,
+            last_synced_block_number: sync_log.last_synced_block_number,
+            token_standard: sync_log.token_standard,
+        }
+    }
+}
+
+/// Creates or updates a chain and (re)starts its listener.
+pub async fn add_chain(
+    _: Admin,
+    State(state): State<AppState>,
+    Json(request): Json<NewChainRequest>,
+) -> Result<(StatusCode, Json<ChainResponse>), StatusCode> {
+    let internal_error = |_| StatusCode::INTERNAL_SERVER_ERROR;
+
+    if request.name.trim().is_empty() || request.rpc_url.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    EvmChains::upsert(
+        request.id as i64,
+        request.name.trim(),
+        Some(request.rpc_url.trim()),
+        request.block_time,
+        &state.db_pool,
+    )
+    .await
+    .map_err(internal_error)?;
+    EvmChains::update_ws_url(request.id, request.ws_url.as_deref(), &state.db_pool)
+        .await
+        .map_err(internal_error)?;
+    EvmChains::update_confirmations(request.id, request.confirmations, &state.db_pool)
+        .await
+        .map_err(internal_error)?;
+
+    let chain = EvmChains::fetch_by_id(request.id, &state.db_pool)
+        .await
+        .map_err(internal_error)?;
+    state.listeners.start(&chain).await;
+
+    Ok((StatusCode::CREATED, Json(ChainResponse::from(chain))))
+}
+
+/// Stops indexing a chain and removes it. Its contracts have to be removed first.
+pub async fn remove_chain(
+    _: Admin,
+    Path(chain_id): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    let internal_error = |_| StatusCode::INTERNAL_SERVER_ERROR;
+
+    match EvmChains::fetch_by_id(chain_id, &state.db_pool).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+    let contracts = EvmSyncLogs::find_all_by_chain_id(chain_id, &state.db_pool)
+        .await
+        .map_err(internal_error)?;
+    if !contracts.is_empty() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    state.listeners.stop(chain_id).await;
+
+    let mut tx = state.db_pool.begin().await.map_err(internal_error)?;
+    EvmBlocks::delete_by_chain(chain_id, &mut tx)
+        .await
+        .map_err(internal_error)?;
+    TransferRollbacks::delete_by_chain(chain_id, &mut tx)
+        .await
+        .map_err(internal_error)?;
+    if !EvmChains::delete(chain_id, &mut tx)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Starts tracking a contract on a chain, restarting the chain's listener so it is
+/// picked up right away.
+pub async fn add_contract(
+    _: Admin,
+    State(state): State<AppState>,
+    Json(request): Json<NewContractRequest>,
+) -> Result<(StatusCode, Json<ContractResponse>), StatusCode> {
+    let internal_error = |_| StatusCode::INTERNAL_SERVER_ERROR;
+
+    let address = parse_address(&request.address)?.to_string();
+    let abi = request
+        .abi
+        .as_ref()
+        .map(|abi| {
+            parse_abi(abi)
+                .ok()
+                .and_then(|abi| serde_json::to_value(abi).ok())
+                .ok_or(StatusCode::BAD_REQUEST)
+        })
+        .transpose()?;
+
+    match EvmChains::fetch_by_id(request.chain_id, &state.db_pool).await {
+        Ok(_) => {}
+        Err(sqlx::Error::RowNotFound) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    let sync_log =
+        EvmSyncLogs::find_or_create_by_address(&address, request.chain_id, &state.db_pool)
+            .await
+            .map_err(internal_error)?;
+    if let Some(start_block) = request.start_block {
+        sync_log
+            .update_start_block_number(start_block, &state.db_pool)
+            .await
+            .map_err(internal_error)?;
+    }
+    if let Some(abi) = &abi {
+        sync_log
+            .update_abi(abi, &state.db_pool)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    state
+        .listeners
+        .restart(request.chain_id)
+        .await
+        .map_err(internal_error)?;
+
+    let sync_log =
+        EvmSyncLogs::find_or_create_by_address(&address, request.chain_id, &state.db_pool)
+            .await
+            .map_err(internal_error)?;
+    Ok((StatusCode::CREATED, Json(ContractResponse::from(sync_log))))
+}
+
+/// Stops tracking a contract and deletes everything indexed for it. The chain's
+/// listener is stopped meanwhile, so none of it is written again.
+pub async fn remove_contract(
+    _: Admin,
+    token: TokenPath,
+    State(state): State<AppState>,
+) -> Result<StatusCode, StatusCode> {
+    // Untracked contracts are rejected before the chain's listener is interrupted.
+    token.sync_log(&state.db_pool).await?;
+    let TokenPath { chain_id, address } = token;
+
+    let was_running = state.listeners.stop(chain_id).await;
+    let deleted = delete_contract(chain_id, &address, &state).await;
+    if was_running {
+        state
+            .listeners
+            .restart(chain_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    match deleted {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Deletes everything indexed for a contract and broadcasts its transfers and events with
+/// `removed` set, recording the removal so streams resuming from before it reset.
+async fn delete_contract(
+    chain_id: u64,
+    address: &str,
+    state: &AppState,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = state.db_pool.begin().await?;
+
+    let last_transfer_id = Erc20Transfers::max_id_by_chain(chain_id, &mut tx).await?;
+    let removed = Erc20Transfers::delete_by_contract(chain_id, address, &mut tx).await?;
+    if let (Some(first_removed_id), Some(first_block)) = (
+        removed.iter().map(|transfer| transfer.id).min(),
+        removed.iter().map(|transfer| transfer.block_number).min(),
+    ) {
+        TransferRollbacks::create(
+            chain_id,
+            (first_block as u64).saturating_sub(1),
+            first_removed_id,
+            last_transfer_id,
+            &mut tx,
+        )
+        .await?;
+    }
+    Erc20Approvals::delete_by_contract(chain_id, address, &mut tx).await?;
+    let removed_nfts = NftTransfers::delete_by_contract(chain_id, address, &mut tx).await?;
+    let removed_events = ContractEvents::delete_by_contract(chain_id, address, &mut tx).await?;
+    TokenBalances::delete_by_contract(chain_id, address, &mut tx).await?;
+    TokenTransferRollups::delete_by_contract(chain_id, address, &mut tx).await?;
+    TokenMetadata::delete_by_contract(chain_id, address, &mut tx).await?;
+    let deleted = EvmSyncLogs::delete(chain_id, address, &mut tx).await?;
+
+    tx.commit().await?;
+
+    let removed = removed.into_iter().map(TransferResponse::from).chain(
+        removed_nfts
+            .into_iter()
+            .map(TransferResponse::from_nft_transfer),
+    );
+    for mut transfer_response in removed {
+        transfer_response.removed = true;
+        let _ = state.transfer_tx.send(transfer_response);
+    }
+    for event in removed_events {
+        let mut event_response = ContractEventResponse::from(event);
+        event_response.removed = true;
+        let _ = state.event_tx.send(event_response);
+    }
+
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_compared_in_full() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret-but-longer"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+}
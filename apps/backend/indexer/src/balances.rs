@@ -0,0 +1,99 @@
+use std::{error::Error, str::FromStr};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::request::TransactionRequest,
+    sol_types::SolCall,
+};
+use database::entity::{
+    evm_chains::EvmChains, evm_sync_logs::EvmSyncLogs, token_balances::TokenBalances,
+};
+use sqlx::{types::BigDecimal, Pool, Postgres};
+
+use crate::error::AppError;
+use crate::metadata::IERC20;
+
+/// A holder's balance as reconstructed from transfers next to `balanceOf` on chain.
+#[derive(Debug)]
+pub struct BalanceCheck {
+    pub holder: Address,
+    pub indexed: BigDecimal,
+    pub on_chain: U256,
+}
+
+impl BalanceCheck {
+    pub fn matches(&self) -> bool {
+        BigDecimal::from_str(&self.on_chain.to_string())
+            .is_ok_and(|on_chain| on_chain == self.indexed)
+    }
+}
+
+/// Reads `balanceOf(holder)` as of `block_number`. Needs an archive node for old blocks.
+pub async fn fetch_balance(
+    provider: &impl Provider,
+    contract: Address,
+    holder: Address,
+    block_number: u64,
+) -> Result<U256, Box<dyn Error + Send + Sync>> {
+    let tx = TransactionRequest::default()
+        .to(contract)
+        .input(IERC20::balanceOfCall { owner: holder }.abi_encode().into());
+    let data = provider
+        .call(&tx)
+        .block(BlockId::number(block_number))
+        .await?;
+    Ok(IERC20::balanceOfCall::abi_decode_returns(&data, false)?._0)
+}
+
+/// Spot-checks the balance ledger of a token: compares the balances of up to `sample`
+/// random holders at `block_number` with `balanceOf` at the same block.
+///
+/// Defaults to the contract's sync cursor, the newest block the ledger covers. Returns
+/// the block that was checked together with every comparison. Only reads: contracts
+/// that are not tracked on the chain, or not indexed from their deployment, are an error.
+pub async fn verify_balances(
+    chain_id: u64,
+    contract_address: &str,
+    block_number: Option<u64>,
+    sample: i64,
+    db_pool: &Pool<Postgres>,
+) -> Result<(u64, Vec<BalanceCheck>), Box<dyn Error + Send + Sync>> {
+    let contract = Address::from_str(contract_address)
+        .map_err(|_| AppError::InvalidAddress(contract_address.to_string()))?;
+    // Stored checksummed, whatever the case it was given in.
+    let contract_address = contract.to_string();
+
+    let sync_log = EvmSyncLogs::find_by_address(&contract_address, chain_id, db_pool)
+        .await?
+        .ok_or_else(|| AppError::ContractNotTracked(contract_address.clone(), chain_id))?;
+    // A ledger missing the transfers before indexing started cannot match the chain.
+    if !sync_log.full_history {
+        return Err(AppError::PartialHistory(contract_address, chain_id).into());
+    }
+    let block_number = block_number.unwrap_or(sync_log.last_synced_block_number as u64);
+
+    let chain = EvmChains::fetch_by_id(chain_id, db_pool).await?;
+    let rpc_url = chain
+        .rpc_url
+        .ok_or_else(|| AppError::MissingEnvVar("RPC_URL for chain".into()))?;
+    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
+
+    let balances =
+        TokenBalances::sample_at_block(chain_id, &contract_address, block_number, sample, db_pool)
+            .await?;
+
+    let mut checks = Vec::with_capacity(balances.len());
+    for balance in balances {
+        let holder = Address::from_slice(&balance.holder_address);
+        let on_chain = fetch_balance(&provider, contract, holder, block_number).await?;
+        checks.push(BalanceCheck {
+            holder,
+            indexed: balance.balance,
+            on_chain,
+        });
+    }
+
+    Ok((block_number, checks))
+}
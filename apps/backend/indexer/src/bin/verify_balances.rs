@@ -0,0 +1,65 @@
+//! Compares balances reconstructed from indexed transfers with on-chain `balanceOf`.
+//!
+//! Usage: `verify_balances <chain_id> <contract_address> [block] [sample]`
+
+use std::{env, process::ExitCode};
+
+use database::initialize_database;
+use dotenvy::dotenv;
+use indexer::balances::verify_balances;
+
+#[tokio::main]
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (Some(chain_id), Some(contract_address)) = (args.first(), args.get(1)) else {
+        eprintln!("Usage: verify_balances <chain_id> <contract_address> [block] [sample]");
+        return Ok(ExitCode::FAILURE);
+    };
+    let chain_id = chain_id.parse::<u64>()?;
+    let block_number = args.get(2).map(|block| block.parse::<u64>()).transpose()?;
+    let sample = args
+        .get(3)
+        .map(|sample| sample.parse::<i64>())
+        .transpose()?;
+
+    let db_pool = initialize_database().await?;
+    let (block_number, checks) = verify_balances(
+        chain_id,
+        contract_address,
+        block_number,
+        sample.unwrap_or(20),
+        &db_pool,
+    )
+    .await?;
+
+    // An empty sample proves nothing, e.g. when nothing is indexed up to the block yet.
+    if checks.is_empty() {
+        eprintln!("No holders with a balance at block {block_number}");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let mut mismatches = 0;
+    for check in &checks {
+        if check.matches() {
+            println!("ok       {} {}", check.holder, check.indexed);
+        } else {
+            mismatches += 1;
+            println!(
+                "MISMATCH {} indexed {} on chain {}",
+                check.holder, check.indexed, check.on_chain
+            );
+        }
+    }
+    println!(
+        "Checked {} holders at block {block_number}: {mismatches} mismatches",
+        checks.len()
+    );
+
+    Ok(if mismatches == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,503 @@
+use std::{future::Future, time::Duration};
+
+use alloy::{
+    providers::Provider,
+    rpc::types::{Filter, Log},
+    transports::{TransportError, TransportErrorKind, TransportResult},
+};
+
+/// Blocks per `eth_getLogs` request before we know anything about the chain.
+pub const DEFAULT_BLOCK_RANGE: u64 = 1_000;
+/// Upper bound for the window, even when responses stay small.
+pub const MAX_BLOCK_RANGE: u64 = 50_000;
+/// Responses with fewer logs than this let the window grow.
+pub const SMALL_RESPONSE_LOGS: usize = 1_000;
+/// Requests that have to succeed before a rejected window size may be tried again, so
+/// a dense stretch of logs does not cap the window for good.
+pub const CEILING_RESET_REQUESTS: u32 = 100;
+
+/// Error fragments RPC providers use when a `eth_getLogs` request spans too many
+/// blocks or would return too many logs.
+const RANGE_LIMIT_MESSAGES: &[&str] = &[
+    "query returned more than",
+    "block range too large",
+    "block range is too wide",
+    "exceed maximum block range",
+    "range too large",
+    "log response size exceeded",
+    "response size exceeded",
+    "too many results",
+];
+
+/// Error fragments RPC providers use when they throttle requests, whatever their size.
+const RATE_LIMIT_MESSAGES: &[&str] = &[
+    "rate limit",
+    "too many requests",
+    "request limit reached",
+    "credits limited",
+    "compute units per second",
+];
+
+/// Error code for "limit exceeded" from EIP-1474, which providers send when throttling.
+const LIMIT_EXCEEDED_CODE: i64 = -32005;
+/// HTTP status of throttled requests, also used as error code by some providers.
+const TOO_MANY_REQUESTS: i64 = 429;
+
+/// Attempts at a throttled request before the error is returned.
+pub const RATE_LIMIT_RETRIES: u32 = 5;
+/// Delay before retrying a throttled request, doubled on every further attempt.
+pub const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Something we can run `eth_getLogs` against.
+pub trait LogSource {
+    fn query_logs<'a>(
+        &'a self,
+        filter: &'a Filter,
+    ) -> impl Future<Output = TransportResult<Vec<Log>>> + Send + 'a;
+}
+
+impl<P: Provider> LogSource for P {
+    fn query_logs<'a>(
+        &'a self,
+        filter: &'a Filter,
+    ) -> impl Future<Output = TransportResult<Vec<Log>>> + Send + 'a {
+        Provider::get_logs(self, filter)
+    }
+}
+
+/// The number of blocks requested per `eth_getLogs` call, grown while responses are
+/// small and halved whenever the provider rejects a request.
+///
+/// The last rejected size is remembered and growth stays below it, closing in on it
+/// by halving the gap instead of doubling straight back into the same error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRange {
+    size: u64,
+    max: u64,
+    ceiling: Option<u64>,
+    /// Requests that succeeded since the ceiling was set.
+    since_rejected: u32,
+}
+
+impl Default for BlockRange {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCK_RANGE, MAX_BLOCK_RANGE)
+    }
+}
+
+impl BlockRange {
+    pub fn new(size: u64, max: u64) -> Self {
+        let max = max.max(1);
+        Self {
+            size: size.clamp(1, max),
+            max,
+            ceiling: None,
+            since_rejected: 0,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn grow(&mut self) {
+        self.since_rejected = self.since_rejected.saturating_add(1);
+        if self.since_rejected >= CEILING_RESET_REQUESTS {
+            self.ceiling = None;
+        }
+
+        let grown = self.size.saturating_mul(2);
+        self.size = match self.ceiling {
+            Some(ceiling) if grown >= ceiling => self.size + ceiling.saturating_sub(self.size) / 2,
+            _ => grown,
+        }
+        .min(self.max);
+    }
+
+    fn shrink(&mut self, rejected: u64) {
+        self.ceiling = Some(rejected);
+        self.since_rejected = 0;
+        self.size = (rejected / 2).max(1);
+    }
+}
+
+/// Whether the provider rejected a request for the blocks or logs it spans.
+pub fn is_range_limit_error(err: &TransportError) -> bool {
+    let Some(payload) = err.as_error_resp() else {
+        return false;
+    };
+
+    let message = payload.message.to_lowercase();
+    RANGE_LIMIT_MESSAGES
+        .iter()
+        .any(|fragment| message.contains(fragment))
+}
+
+/// Whether the provider throttled a request; a smaller one would be throttled too.
+pub fn is_rate_limit_error(err: &TransportError) -> bool {
+    if let TransportError::Transport(TransportErrorKind::HttpError(http_err)) = err {
+        return http_err.is_rate_limit_err();
+    }
+    let Some(payload) = err.as_error_resp() else {
+        return false;
+    };
+    if is_range_limit_error(err) {
+        return false;
+    }
+
+    let message = payload.message.to_lowercase();
+    payload.code == LIMIT_EXCEEDED_CODE
+        || payload.code == TOO_MANY_REQUESTS
+        || RATE_LIMIT_MESSAGES
+            .iter()
+            .any(|fragment| message.contains(fragment))
+}
+
+/// Fetches the logs matching `filter` from `from_block` onwards, as many blocks as
+/// `range` allows without going past `latest_block`.
+///
+/// Returns the last block covered together with its logs. Requests rejected for
+/// their size are retried with half the window until a single block is left;
+/// throttled requests are retried with the same window after a growing delay.
+pub async fn fetch_logs(
+    source: &impl LogSource,
+    filter: &Filter,
+    from_block: u64,
+    latest_block: u64,
+    range: &mut BlockRange,
+) -> TransportResult<(u64, Vec<Log>)> {
+    let mut throttled = 0;
+    loop {
+        let to_block = from_block
+            .saturating_add(range.size() - 1)
+            .min(latest_block);
+        let span = to_block - from_block + 1;
+
+        let filter = filter.clone().from_block(from_block).to_block(to_block);
+        match source.query_logs(&filter).await {
+            Ok(logs) => {
+                if span == range.size() && logs.len() < SMALL_RESPONSE_LOGS {
+                    range.grow();
+                }
+                return Ok((to_block, logs));
+            }
+            Err(err) if span > 1 && is_range_limit_error(&err) => range.shrink(span),
+            Err(err) if throttled < RATE_LIMIT_RETRIES && is_rate_limit_error(&err) => {
+                tokio::time::sleep(RATE_LIMIT_BACKOFF * 2u32.pow(throttled)).await;
+                throttled += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, LogData};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serves logs from memory and enforces limits the way hosted RPC providers do.
+    struct MockRpc {
+        log_blocks: Vec<u64>,
+        max_range: u64,
+        max_results: usize,
+        calls: AtomicUsize,
+    }
+
+    impl MockRpc {
+        fn new(log_blocks: Vec<u64>, max_range: u64, max_results: usize) -> Self {
+            Self {
+                log_blocks,
+                max_range,
+                max_results,
+                calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    fn error(code: i64, message: &str) -> TransportError {
+        let payload = format!(r#"{{"code":{code},"message":"{message}"}}"#);
+        TransportError::ErrorResp(serde_json::from_str(&payload).unwrap())
+    }
+
+    fn log_at(block_number: u64) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(vec![], Default::default()),
+            },
+            block_number: Some(block_number),
+            ..Default::default()
+        }
+    }
+
+    impl LogSource for MockRpc {
+        fn query_logs<'a>(
+            &'a self,
+            filter: &'a Filter,
+        ) -> impl Future<Output = TransportResult<Vec<Log>>> + Send + 'a {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let from_block = filter.get_from_block().unwrap();
+            let to_block = filter.get_to_block().unwrap();
+
+            let result = if to_block - from_block + 1 > self.max_range {
+                Err(error(-32600, "block range too large"))
+            } else {
+                let logs: Vec<Log> = self
+                    .log_blocks
+                    .iter()
+                    .filter(|block| (from_block..=to_block).contains(block))
+                    .map(|block| log_at(*block))
+                    .collect();
+                if logs.len() > self.max_results {
+                    Err(error(-32005, "query returned more than 10000 results"))
+                } else {
+                    Ok(logs)
+                }
+            };
+
+            async move { result }
+        }
+    }
+
+    #[tokio::test]
+    async fn grows_window_while_responses_are_small() {
+        let rpc = MockRpc::new(vec![5], 100_000, 10_000);
+        let mut range = BlockRange::new(100, 1_000);
+
+        let (to_block, logs) = fetch_logs(&rpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap();
+
+        assert_eq!(to_block, 99);
+        assert_eq!(logs.len(), 1);
+        assert_eq!(range.size(), 200);
+    }
+
+    #[tokio::test]
+    async fn never_grows_past_max() {
+        let rpc = MockRpc::new(vec![], 100_000, 10_000);
+        let mut range = BlockRange::new(800, 1_000);
+
+        fetch_logs(&rpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap();
+
+        assert_eq!(range.size(), 1_000);
+    }
+
+    #[tokio::test]
+    async fn stops_at_latest_block() {
+        let rpc = MockRpc::new(vec![], 100_000, 10_000);
+        let mut range = BlockRange::new(1_000, 1_000);
+
+        let (to_block, _) = fetch_logs(&rpc, &Filter::new(), 50, 60, &mut range)
+            .await
+            .unwrap();
+
+        assert_eq!(to_block, 60);
+        assert_eq!(range.size(), 1_000);
+    }
+
+    #[tokio::test]
+    async fn halves_window_when_range_is_too_large() {
+        let rpc = MockRpc::new(vec![], 300, 10_000);
+        let mut range = BlockRange::new(1_000, 10_000);
+
+        let (to_block, _) = fetch_logs(&rpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap();
+
+        assert_eq!(to_block, 249);
+        assert_eq!(rpc.calls.load(Ordering::SeqCst), 3);
+        // The window grows again, but stays below the 500 blocks that were rejected.
+        assert_eq!(range.size(), 375);
+    }
+
+    #[tokio::test]
+    async fn settles_below_a_rejected_size() {
+        let rpc = MockRpc::new(vec![], 300, 10_000);
+        let mut range = BlockRange::new(1_000, 10_000);
+
+        let mut from_block = 0;
+        for _ in 0..20 {
+            let (to_block, _) = fetch_logs(&rpc, &Filter::new(), from_block, u64::MAX, &mut range)
+                .await
+                .unwrap();
+            from_block = to_block + 1;
+        }
+        let calls = rpc.calls.load(Ordering::SeqCst);
+
+        for _ in 0..10 {
+            let (to_block, _) = fetch_logs(&rpc, &Filter::new(), from_block, u64::MAX, &mut range)
+                .await
+                .unwrap();
+            from_block = to_block + 1;
+        }
+
+        // Once settled, every request succeeds the first time.
+        assert_eq!(rpc.calls.load(Ordering::SeqCst), calls + 10);
+        assert!(range.size() <= 300);
+    }
+
+    #[test]
+    fn rejected_size_is_tried_again_eventually() {
+        let mut range = BlockRange::new(1_000, 10_000);
+        range.shrink(1_000);
+
+        for _ in 1..CEILING_RESET_REQUESTS {
+            range.grow();
+        }
+        assert_eq!(range.size(), 999);
+
+        range.grow();
+        assert_eq!(range.size(), 1_998);
+    }
+
+    #[tokio::test]
+    async fn halves_window_when_too_many_results() {
+        let rpc = MockRpc::new((0..100).collect(), 100_000, 10);
+        let mut range = BlockRange::new(100, 1_000);
+
+        let (to_block, logs) = fetch_logs(&rpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap();
+
+        assert_eq!(to_block, 5);
+        assert_eq!(logs.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_single_block() {
+        let rpc = MockRpc::new(vec![7, 7, 7], 100_000, 2);
+        let mut range = BlockRange::new(16, 1_000);
+
+        let err = fetch_logs(&rpc, &Filter::new(), 7, 10_000, &mut range)
+            .await
+            .unwrap_err();
+
+        assert!(is_range_limit_error(&err));
+        assert_eq!(range.size(), 1);
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        struct FailingRpc;
+
+        impl LogSource for FailingRpc {
+            async fn query_logs(&self, _: &Filter) -> TransportResult<Vec<Log>> {
+                Err(error(-32000, "header not found"))
+            }
+        }
+
+        let mut range = BlockRange::new(100, 1_000);
+        let err = fetch_logs(&FailingRpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap_err();
+
+        assert!(!is_range_limit_error(&err));
+        assert_eq!(range.size(), 100);
+    }
+
+    /// Throttles the first `throttled` requests, then serves no logs.
+    struct ThrottlingRpc {
+        throttled: usize,
+        calls: AtomicUsize,
+    }
+
+    impl LogSource for ThrottlingRpc {
+        fn query_logs<'a>(
+            &'a self,
+            _: &'a Filter,
+        ) -> impl Future<Output = TransportResult<Vec<Log>>> + Send + 'a {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let result = if call < self.throttled {
+                Err(error(-32005, "rate limit exceeded"))
+            } else {
+                Ok(vec![])
+            };
+            async move { result }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_throttled_requests_after_a_delay() {
+        let rpc = ThrottlingRpc {
+            throttled: 3,
+            calls: AtomicUsize::new(0),
+        };
+        let mut range = BlockRange::new(1_000, 1_000);
+
+        let started = tokio::time::Instant::now();
+        let (to_block, _) = fetch_logs(&rpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap();
+
+        assert_eq!(to_block, 999);
+        assert_eq!(range.size(), 1_000);
+        assert_eq!(rpc.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(started.elapsed(), RATE_LIMIT_BACKOFF * 7);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_when_throttling_persists() {
+        let rpc = ThrottlingRpc {
+            throttled: usize::MAX,
+            calls: AtomicUsize::new(0),
+        };
+        let mut range = BlockRange::new(1_000, 1_000);
+
+        let err = fetch_logs(&rpc, &Filter::new(), 0, 10_000, &mut range)
+            .await
+            .unwrap_err();
+
+        assert!(is_rate_limit_error(&err));
+        assert_eq!(range.size(), 1_000);
+        assert_eq!(
+            rpc.calls.load(Ordering::SeqCst),
+            RATE_LIMIT_RETRIES as usize + 1
+        );
+    }
+
+    #[test]
+    fn recognises_provider_limit_errors() {
+        assert!(is_range_limit_error(&error(
+            -32602,
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"
+        )));
+        assert!(is_range_limit_error(&error(
+            -32000,
+            "exceed maximum block range: 5000"
+        )));
+        assert!(is_range_limit_error(&error(
+            -32005,
+            "query returned more than 10000 results"
+        )));
+        assert!(!is_range_limit_error(&error(
+            -32005,
+            "query timeout exceeded"
+        )));
+        assert!(!is_range_limit_error(&error(-32005, "rate limit exceeded")));
+        assert!(!is_range_limit_error(&error(-32000, "execution reverted")));
+    }
+
+    #[test]
+    fn recognises_rate_limit_errors() {
+        assert!(is_rate_limit_error(&error(-32005, "limit exceeded")));
+        assert!(is_rate_limit_error(&error(429, "Too Many Requests")));
+        assert!(is_rate_limit_error(&error(
+            -32007,
+            "100/second request limit reached - reduce calls per second"
+        )));
+        assert!(is_rate_limit_error(&TransportErrorKind::http_error(
+            429,
+            String::new()
+        )));
+        assert!(!is_rate_limit_error(&error(
+            -32005,
+            "query returned more than 10000 results"
+        )));
+        assert!(!is_rate_limit_error(&error(-32000, "header not found")));
+    }
+}
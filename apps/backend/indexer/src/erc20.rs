@@ -1,33 +1,109 @@
 use alloy::{
-    primitives::{Address, U256},
+    primitives::{Address, B256, U256},
     rpc::types::Log,
 };
 use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
+
+/// keccak256("Transfer(address,address,uint256)") in other words - hash of the event signature
+pub const TRANSFER_EVENT_SIGNATURE: B256 = B256::new([
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+]);
+
+/// keccak256("Approval(address,address,uint256)")
+pub const APPROVAL_EVENT_SIGNATURE: B256 = B256::new([
+    0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b, 0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e, 0x84, 0xf3,
+    0xdd, 0x03, 0x14, 0xc0, 0xf7, 0xb2, 0x29, 0x1e, 0x5b, 0x20, 0x0a, 0xc8, 0xc7, 0xc3, 0xb9, 0x25,
+]);
+
+/// keccak256("Deposit(address,uint256)"), emitted by WETH-style tokens when wrapping
+pub const DEPOSIT_EVENT_SIGNATURE: B256 = B256::new([
+    0xe1, 0xff, 0xfc, 0xc4, 0x92, 0x3d, 0x04, 0xb5, 0x59, 0xf4, 0xd2, 0x9a, 0x8b, 0xfc, 0x6c, 0xda,
+    0x04, 0xeb, 0x5b, 0x0d, 0x3c, 0x46, 0x07, 0x51, 0xc2, 0x40, 0x2c, 0x5c, 0x5c, 0xc9, 0x10, 0x9c,
+]);
+
+/// keccak256("Withdrawal(address,uint256)"), emitted by WETH-style tokens when unwrapping
+pub const WITHDRAWAL_EVENT_SIGNATURE: B256 = B256::new([
+    0x7f, 0xcf, 0x53, 0x2c, 0x15, 0xf0, 0xa6, 0xdb, 0x0b, 0xd6, 0xd0, 0xe0, 0x38, 0xbe, 0xa7, 0x1d,
+    0x30, 0xd8, 0x08, 0xc7, 0xd9, 0x8c, 0xb3, 0xbf, 0x72, 0x68, 0xa9, 0x5b, 0xf5, 0x08, 0x1b, 0x65,
+]);
+
+/// How a movement changes the token's supply. Mints and wraps create tokens, burns and
+/// unwraps destroy them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferKind {
+    #[default]
+    Transfer,
+    Mint,
+    Burn,
+    Wrap,
+    Unwrap,
+}
+
+impl TransferKind {
+    /// Classifies a `Transfer` by its endpoints: from the zero address is a mint, to
+    /// the zero address a burn.
+    pub fn classify(from: Address, to: Address) -> Self {
+        if from == Address::ZERO {
+            TransferKind::Mint
+        } else if to == Address::ZERO {
+            TransferKind::Burn
+        } else {
+            TransferKind::Transfer
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferKind::Transfer => "transfer",
+            TransferKind::Mint => "mint",
+            TransferKind::Burn => "burn",
+            TransferKind::Wrap => "wrap",
+            TransferKind::Unwrap => "unwrap",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "transfer" => Some(TransferKind::Transfer),
+            "mint" => Some(TransferKind::Mint),
+            "burn" => Some(TransferKind::Burn),
+            "wrap" => Some(TransferKind::Wrap),
+            "unwrap" => Some(TransferKind::Unwrap),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Erc20Transfer {
     pub from: Address,
     pub to: Address,
     pub amount: U256,
+    pub kind: TransferKind,
 }
 
 impl Erc20Transfer {
+    /// Decodes a `Transfer`, or a WETH-style `Deposit`/`Withdrawal`, which is stored as
+    /// a wrap from or an unwrap to the zero address.
     pub fn from_log(log: &Log) -> Option<Self> {
-        let transfer_signature = alloy::primitives::FixedBytes::<32>::from([
-            0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37,
-            0x8d, 0xaa, 0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d,
-            0xf5, 0x23, 0xb3, 0xef,
-        ]); // keccak256("Transfer(address,address,uint256)") in other words - hash of the event signature
-
-        if log.topics().is_empty() || log.topics()[0] != transfer_signature {
-            return None;
+        match log.topics().first() {
+            Some(&TRANSFER_EVENT_SIGNATURE) => {}
+            Some(&DEPOSIT_EVENT_SIGNATURE) => return Self::from_wrap_log(log, TransferKind::Wrap),
+            Some(&WITHDRAWAL_EVENT_SIGNATURE) => {
+                return Self::from_wrap_log(log, TransferKind::Unwrap)
+            }
+            _ => return None,
         }
 
         // For ERC-20 Transfer events:
         // topics[1] = from address (indexed)
         // topics[2] = to address (indexed)
         // data = amount (uint256)
-        if log.topics().len() < 3 {
+        // ERC-721 transfers share the signature but index the token id as a fourth topic.
+        if log.topics().len() != 3 {
             return None;
         }
 
@@ -40,6 +116,225 @@ impl Erc20Transfer {
         let amount_bytes: [u8; 32] = log.inner.data.data[..32].try_into().ok()?;
         let amount = U256::from_be_bytes(amount_bytes);
 
-        Some(Erc20Transfer { from, to, amount })
+        Some(Erc20Transfer {
+            from,
+            to,
+            amount,
+            kind: TransferKind::classify(from, to),
+        })
+    }
+
+    fn from_wrap_log(log: &Log, kind: TransferKind) -> Option<Self> {
+        // topics[1] = dst of a deposit or src of a withdrawal, data = wad
+        let topics = log.topics();
+        if topics.len() != 2 {
+            return None;
+        }
+
+        let account = Address::from_slice(&topics[1][12..]);
+        let amount_bytes: [u8; 32] = log.inner.data.data.get(..32)?.try_into().ok()?;
+        let (from, to) = match kind {
+            TransferKind::Wrap => (Address::ZERO, account),
+            _ => (account, Address::ZERO),
+        };
+
+        Some(Erc20Transfer {
+            from,
+            to,
+            amount: U256::from_be_bytes(amount_bytes),
+            kind,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Erc20Approval {
+    pub owner: Address,
+    pub spender: Address,
+    pub amount: U256,
+}
+
+impl Erc20Approval {
+    pub fn from_log(log: &Log) -> Option<Self> {
+        // topics[1] = owner, topics[2] = spender, data = value. ERC-721 approvals
+        // index the token id instead and carry no data, so they are skipped.
+        let topics = log.topics();
+        if topics.len() != 3 || topics[0] != APPROVAL_EVENT_SIGNATURE {
+            return None;
+        }
+
+        let amount_bytes: [u8; 32] = log.inner.data.data.get(..32)?.try_into().ok()?;
+
+        Some(Erc20Approval {
+            owner: Address::from_slice(&topics[1][12..]),
+            spender: Address::from_slice(&topics[2][12..]),
+            amount: U256::from_be_bytes(amount_bytes),
+        })
+    }
+}
+
+/// Formats a raw token amount using the token's decimals, e.g. `1500000` with 6
+/// decimals becomes `"1.5"`. No precision is lost.
+pub fn format_amount(raw: &BigDecimal, decimals: u8) -> String {
+    let (digits, _) = raw.with_scale(0).into_bigint_and_exponent();
+    BigDecimal::new(digits, decimals as i64)
+        .normalized()
+        .to_plain_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{keccak256, LogData};
+    use std::str::FromStr;
+
+    #[test]
+    fn event_signatures_match_their_hashes() {
+        assert_eq!(
+            TRANSFER_EVENT_SIGNATURE,
+            keccak256("Transfer(address,address,uint256)")
+        );
+        assert_eq!(
+            APPROVAL_EVENT_SIGNATURE,
+            keccak256("Approval(address,address,uint256)")
+        );
+        assert_eq!(
+            DEPOSIT_EVENT_SIGNATURE,
+            keccak256("Deposit(address,uint256)")
+        );
+        assert_eq!(
+            WITHDRAWAL_EVENT_SIGNATURE,
+            keccak256("Withdrawal(address,uint256)")
+        );
+    }
+
+    fn log(topics: Vec<B256>, amount: U256) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(topics, amount.to_be_bytes::<32>().to_vec().into()),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classifies_mints_and_burns() {
+        let holder = Address::repeat_byte(1);
+        let transfer = |from: Address, to: Address| {
+            Erc20Transfer::from_log(&log(
+                vec![TRANSFER_EVENT_SIGNATURE, from.into_word(), to.into_word()],
+                U256::from(5),
+            ))
+            .unwrap()
+            .kind
+        };
+
+        assert_eq!(transfer(Address::ZERO, holder), TransferKind::Mint);
+        assert_eq!(transfer(holder, Address::ZERO), TransferKind::Burn);
+        assert_eq!(
+            transfer(holder, Address::repeat_byte(2)),
+            TransferKind::Transfer
+        );
+    }
+
+    #[test]
+    fn skips_erc721_transfers() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        // A token id as fourth topic, and an amount-sized payload that must not matter.
+        let log = log(
+            vec![
+                TRANSFER_EVENT_SIGNATURE,
+                from.into_word(),
+                to.into_word(),
+                U256::from(42).into(),
+            ],
+            U256::from(5),
+        );
+
+        assert!(Erc20Transfer::from_log(&log).is_none());
+    }
+
+    #[test]
+    fn decodes_deposits_and_withdrawals_as_wraps() {
+        let holder = Address::repeat_byte(1);
+
+        let wrap = Erc20Transfer::from_log(&log(
+            vec![DEPOSIT_EVENT_SIGNATURE, holder.into_word()],
+            U256::from(7),
+        ))
+        .unwrap();
+        assert_eq!(wrap.kind, TransferKind::Wrap);
+        assert_eq!((wrap.from, wrap.to), (Address::ZERO, holder));
+        assert_eq!(wrap.amount, U256::from(7));
+
+        let unwrap = Erc20Transfer::from_log(&log(
+            vec![WITHDRAWAL_EVENT_SIGNATURE, holder.into_word()],
+            U256::from(7),
+        ))
+        .unwrap();
+        assert_eq!(unwrap.kind, TransferKind::Unwrap);
+        assert_eq!((unwrap.from, unwrap.to), (holder, Address::ZERO));
+
+        // ERC-4626 and other `Deposit` events have a different signature or topic count.
+        assert!(Erc20Transfer::from_log(&log(
+            vec![
+                DEPOSIT_EVENT_SIGNATURE,
+                holder.into_word(),
+                holder.into_word()
+            ],
+            U256::from(7),
+        ))
+        .is_none());
+    }
+
+    #[test]
+    fn decodes_approval() {
+        let owner = Address::repeat_byte(1);
+        let spender = Address::repeat_byte(2);
+        let log = Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(
+                    vec![
+                        APPROVAL_EVENT_SIGNATURE,
+                        owner.into_word(),
+                        spender.into_word(),
+                    ],
+                    U256::MAX.to_be_bytes::<32>().to_vec().into(),
+                ),
+            },
+            ..Default::default()
+        };
+
+        let approval = Erc20Approval::from_log(&log).unwrap();
+        assert_eq!(approval.owner, owner);
+        assert_eq!(approval.spender, spender);
+        assert_eq!(approval.amount, U256::MAX);
+        assert!(Erc20Transfer::from_log(&log).is_none());
+    }
+
+    #[test]
+    fn formats_amount_with_decimals() {
+        let raw = BigDecimal::from_str("1500000").unwrap();
+        assert_eq!(format_amount(&raw, 6), "1.5");
+        assert_eq!(format_amount(&raw, 0), "1500000");
+    }
+
+    #[test]
+    fn keeps_sub_unit_and_large_amounts_exact() {
+        let dust = BigDecimal::from_str("1").unwrap();
+        assert_eq!(format_amount(&dust, 18), "0.000000000000000001");
+
+        let max = BigDecimal::from_str(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+        )
+        .unwrap();
+        assert_eq!(
+            format_amount(&max, 18),
+            "115792089237316195423570985008687907853269984665640564039457.584007913129639935"
+        );
+        assert_eq!(format_amount(&BigDecimal::from(0), 18), "0");
     }
 }
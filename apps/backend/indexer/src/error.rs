@@ -9,6 +9,31 @@ pub enum AppError {
 
     #[error("Invalid ChainID: `{0}`")]
     InvalidChainID(String),
+
+    #[error("Invalid address: `{0}`")]
+    InvalidAddress(String),
+
+    #[error(
+        "Chain {0} is not configured, add it to `CHAIN_RPC_URLS` or through POST /admin/chains"
+    )]
+    ChainNotConfigured(u64),
+
+    #[error("No chains configured, set `CHAIN_RPC_URLS` or add one through POST /admin/chains")]
+    NoChainsConfigured,
+
+    #[error("Contract `{0}` is not tracked on chain {1}")]
+    ContractNotTracked(String, u64),
+
+    #[error(
+        "Contract `{0}` on chain {1} was not indexed from its deployment, its balances are partial"
+    )]
+    PartialHistory(String, u64),
+
+    #[error("Block `{0}` not found")]
+    BlockNotFound(u64),
+
+    #[error("Reorg deeper than {0} blocks")]
+    ReorgTooDeep(u64),
 }
 
 #[cfg(test)]
@@ -29,4 +54,52 @@ mod tests {
         let err = AppError::InvalidChainID("999".into());
         assert_eq!(format!("{}", err), "Invalid ChainID: `999`");
     }
+
+    #[test]
+    fn invalid_address_displays_message() {
+        let err = AppError::InvalidAddress("0x1234".into());
+        assert_eq!(format!("{}", err), "Invalid address: `0x1234`");
+    }
+
+    #[test]
+    fn chain_not_configured_displays_message() {
+        let err = AppError::ChainNotConfigured(10);
+        assert_eq!(
+            format!("{}", err),
+            "Chain 10 is not configured, add it to `CHAIN_RPC_URLS` or through POST /admin/chains"
+        );
+    }
+
+    #[test]
+    fn no_chains_configured_displays_message() {
+        let err = AppError::NoChainsConfigured;
+        assert_eq!(
+            format!("{}", err),
+            "No chains configured, set `CHAIN_RPC_URLS` or add one through POST /admin/chains"
+        );
+    }
+
+    #[test]
+    fn contract_not_tracked_displays_message() {
+        let err = AppError::ContractNotTracked("0x1234".into(), 1);
+        assert_eq!(
+            format!("{}", err),
+            "Contract `0x1234` is not tracked on chain 1"
+        );
+    }
+
+    #[test]
+    fn partial_history_displays_message() {
+        let err = AppError::PartialHistory("0x1234".into(), 1);
+        assert_eq!(
+            format!("{}", err),
+            "Contract `0x1234` on chain 1 was not indexed from its deployment, its balances are partial"
+        );
+    }
+
+    #[test]
+    fn reorg_too_deep_displays_message() {
+        let err = AppError::ReorgTooDeep(128);
+        assert_eq!(format!("{}", err), "Reorg deeper than 128 blocks");
+    }
 }
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use alloy::{
+    dyn_abi::{DynSolValue, EventExt},
+    json_abi::{Event, JsonAbi},
+    primitives::B256,
+    rpc::types::Log,
+};
+use serde_json::{Map, Value};
+
+/// The events a contract was registered with, by topic 0.
+#[derive(Debug, Clone, Default)]
+pub struct ContractAbi {
+    events: HashMap<B256, Event>,
+}
+
+/// A log decoded with its contract's ABI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedContractEvent {
+    pub name: String,
+    /// Canonical signature, e.g. `Swap(address,uint256,uint256,uint256,uint256,address)`.
+    pub signature: String,
+    /// Arguments by name (or `arg<position>` when unnamed), in declaration order.
+    pub params: Value,
+}
+
+/// Parses a registered ABI: either a standard JSON ABI or an array of human-readable
+/// event signatures such as `"event Deposit(address indexed dst, uint256 wad)"`.
+///
+/// Only the events are kept; the result is a JSON ABI suitable for storing.
+pub fn parse_abi(abi: &Value) -> Result<JsonAbi, String> {
+    let items = abi.as_array().ok_or("ABI must be a JSON array")?;
+
+    if items.iter().all(Value::is_string) {
+        let mut parsed = JsonAbi::new();
+        for item in items {
+            let signature = item.as_str().unwrap_or_default();
+            let event = Event::parse(signature).map_err(|err| format!("{signature}: {err}"))?;
+            parsed
+                .events
+                .entry(event.name.clone())
+                .or_default()
+                .push(event);
+        }
+        return Ok(parsed);
+    }
+
+    let full: JsonAbi = serde_json::from_value(abi.clone()).map_err(|err| err.to_string())?;
+    let mut parsed = JsonAbi::new();
+    parsed.events = full.events;
+    Ok(parsed)
+}
+
+impl ContractAbi {
+    pub fn new(abi: &JsonAbi) -> Self {
+        // Anonymous events have no topic 0 to recognise them by.
+        let events = abi
+            .events()
+            .filter(|event| !event.anonymous)
+            .map(|event| (event.selector(), event.clone()))
+            .collect();
+        Self { events }
+    }
+
+    /// Reads the ABI stored for a contract, ignoring one that no longer parses.
+    pub fn from_stored(abi: &Value) -> Option<Self> {
+        serde_json::from_value::<JsonAbi>(abi.clone())
+            .ok()
+            .map(|abi| Self::new(&abi))
+    }
+
+    pub fn selectors(&self) -> impl Iterator<Item = B256> + '_ {
+        self.events.keys().copied()
+    }
+
+    pub fn decode(&self, log: &Log) -> Option<DecodedContractEvent> {
+        let event = self.events.get(log.topics().first()?)?;
+        let decoded = event.decode_log(log.data(), true).ok()?;
+
+        let mut indexed = decoded.indexed.into_iter();
+        let mut body = decoded.body.into_iter();
+        let mut params = Map::new();
+        for (position, input) in event.inputs.iter().enumerate() {
+            // Indexed strings, bytes and arrays only leave their hash in the topic.
+            let value = match input.indexed {
+                true => indexed.next()?,
+                false => body.next()?,
+            };
+            let name = match input.name.is_empty() {
+                true => format!("arg{position}"),
+                false => input.name.clone(),
+            };
+            params.insert(name, to_json(&value));
+        }
+
+        Some(DecodedContractEvent {
+            name: event.name.clone(),
+            signature: event.signature(),
+            params: Value::Object(params),
+        })
+    }
+}
+
+/// Converts a decoded value to JSON. Integers become strings so no precision is lost.
+fn to_json(value: &DynSolValue) -> Value {
+    match value {
+        DynSolValue::Bool(value) => Value::Bool(*value),
+        DynSolValue::Int(value, _) => Value::String(value.to_string()),
+        DynSolValue::Uint(value, _) => Value::String(value.to_string()),
+        DynSolValue::FixedBytes(word, size) => {
+            Value::String(format!("0x{}", hex::encode(&word[..*size])))
+        }
+        DynSolValue::Address(address) => Value::String(address.to_string()),
+        DynSolValue::Function(function) => Value::String(function.to_string()),
+        DynSolValue::Bytes(bytes) => Value::String(format!("0x{}", hex::encode(bytes))),
+        DynSolValue::String(value) => Value::String(value.clone()),
+        DynSolValue::Array(values)
+        | DynSolValue::FixedArray(values)
+        | DynSolValue::Tuple(values) => Value::Array(values.iter().map(to_json).collect()),
+        #[allow(unreachable_patterns)]
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, LogData, U256};
+    use serde_json::json;
+
+    fn deposit_log(dst: Address, wad: U256) -> Log {
+        let selector = alloy::primitives::keccak256("Deposit(address,uint256)");
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(
+                    vec![selector, dst.into_word()],
+                    wad.to_be_bytes::<32>().to_vec().into(),
+                ),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_with_event_signatures() {
+        let abi = parse_abi(&json!(["event Deposit(address indexed dst, uint256 wad)"])).unwrap();
+        let dst = Address::repeat_byte(7);
+
+        let decoded = ContractAbi::new(&abi)
+            .decode(&deposit_log(dst, U256::from(5)))
+            .unwrap();
+
+        assert_eq!(decoded.name, "Deposit");
+        assert_eq!(decoded.signature, "Deposit(address,uint256)");
+        assert_eq!(
+            decoded.params,
+            json!({ "dst": dst.to_string(), "wad": "5" })
+        );
+    }
+
+    #[test]
+    fn keeps_only_events_of_a_json_abi() {
+        let abi = parse_abi(&json!([
+            {
+                "type": "function",
+                "name": "deposit",
+                "inputs": [],
+                "outputs": [],
+                "stateMutability": "payable"
+            },
+            {
+                "type": "event",
+                "name": "Deposit",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "", "type": "address", "indexed": true },
+                    { "name": "", "type": "uint256", "indexed": false }
+                ]
+            }
+        ]))
+        .unwrap();
+        assert!(abi.functions.is_empty());
+
+        let stored = serde_json::to_value(&abi).unwrap();
+        let decoded = ContractAbi::from_stored(&stored)
+            .unwrap()
+            .decode(&deposit_log(Address::ZERO, U256::from(1)))
+            .unwrap();
+        assert_eq!(
+            decoded.params,
+            json!({ "arg0": Address::ZERO.to_string(), "arg1": "1" })
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_events_and_bad_abis() {
+        let abi = parse_abi(&json!([
+            "event Withdrawal(address indexed src, uint256 wad)"
+        ]))
+        .unwrap();
+        assert!(ContractAbi::new(&abi)
+            .decode(&deposit_log(Address::ZERO, U256::from(1)))
+            .is_none());
+
+        assert!(parse_abi(&json!({ "events": [] })).is_err());
+        assert!(parse_abi(&json!(["event Broken(address"])).is_err());
+    }
+}
@@ -1,6 +1,17 @@
+pub mod admin;
+pub mod balances;
+pub mod block_range;
 pub mod erc20;
 pub mod error;
+pub mod events;
+pub mod metadata;
+pub mod nft;
+pub mod registry;
+pub mod reorg;
 pub mod server;
 pub mod service;
+pub mod status;
+pub mod subscription;
+pub mod ws;
 
 pub use erc20::*;
@@ -1,20 +1,14 @@
-use std::{env, time::Duration};
+use std::{env, str::FromStr};
 
+use alloy::primitives::Address;
 use dotenvy::dotenv;
 
 use axum::serve;
 use database::{
     entity::evm_chains::EvmChains, entity::evm_sync_logs::EvmSyncLogs, initialize_database,
 };
-use service::ListenerService;
+use indexer::{error, events, registry::ListenerRegistry, server};
 use tokio::sync::broadcast;
-use tokio::task::JoinSet;
-use tower::{Service, ServiceBuilder, ServiceExt};
-
-mod erc20;
-mod error;
-mod server;
-mod service;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -23,10 +17,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let db_pool = initialize_database().await.unwrap();
 
     let (transfer_tx, _) = broadcast::channel::<server::TransferResponse>(100);
+    let (event_tx, _) = broadcast::channel::<server::ContractEventResponse>(100);
+
+    let listeners = ListenerRegistry::new(db_pool.clone(), transfer_tx.clone(), event_tx.clone());
 
     let app_state = server::AppState {
         db_pool: db_pool.clone(),
         transfer_tx: transfer_tx.clone(),
+        event_tx: event_tx.clone(),
+        listeners: listeners.clone(),
+        admin_token: env::var("ADMIN_API_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty())
+            .map(|token| token.trim().into()),
     };
     let app = server::create_router(app_state);
 
@@ -35,63 +38,133 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         serve(listener, app).await.unwrap();
     });
 
+    // Entries are `chain_id=https://...`; chains missing from the database are added with
+    // that URL, while chains already there keep the settings made through the admin API.
+    let rpc_urls = env::var("CHAIN_RPC_URLS").unwrap_or_default();
+    for entry in rpc_urls.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let (chain_id, rpc_url) = entry
+            .split_once('=')
+            .ok_or_else(|| error::AppError::InvalidChainID(entry.to_string()))?;
+        let chain_id = chain_id
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| error::AppError::InvalidChainID(chain_id.to_string()))?;
+        if EvmChains::create_if_missing(
+            chain_id,
+            &format!("Chain {chain_id}"),
+            rpc_url.trim(),
+            &db_pool,
+        )
+        .await?
+        {
+            println!("Added chain {chain_id} from CHAIN_RPC_URLS");
+        }
+    }
+
+    // Entries are `chain_id=wss://...`; chains with a WebSocket URL are indexed as soon as
+    // a new head is announced instead of on the polling interval.
+    let ws_urls = env::var("CHAIN_WS_URLS").unwrap_or_default();
+    for entry in ws_urls.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let (chain_id, ws_url) = entry
+            .split_once('=')
+            .ok_or_else(|| error::AppError::InvalidChainID(entry.to_string()))?;
+        let chain_id = chain_id
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| error::AppError::InvalidChainID(chain_id.to_string()))?;
+        if !EvmChains::update_ws_url(chain_id, Some(ws_url.trim()), &db_pool).await? {
+            return Err(error::AppError::ChainNotConfigured(chain_id).into());
+        }
+    }
+
+    // Entries are `chain_id=confirmations`; newer blocks are streamed as pending only.
+    let confirmations = env::var("CHAIN_CONFIRMATIONS").unwrap_or_default();
+    for entry in confirmations
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+    {
+        let (chain_id, confirmations) = entry
+            .split_once('=')
+            .ok_or_else(|| error::AppError::InvalidChainID(entry.to_string()))?;
+        let chain_id = chain_id
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| error::AppError::InvalidChainID(chain_id.to_string()))?;
+        if !EvmChains::update_confirmations(chain_id, Some(confirmations.trim().parse()?), &db_pool)
+            .await?
+        {
+            return Err(error::AppError::ChainNotConfigured(chain_id).into());
+        }
+    }
+
     let chains = EvmChains::find_all(&db_pool).await?;
+    let contract_addresses = env::var("CONTRACT_ADDRESSES").unwrap_or_default();
     if chains.is_empty() {
-        eprintln!("No chains configured in database");
-        return Ok(());
+        // The contracts would otherwise be dropped without anything being indexed.
+        if !contract_addresses.trim().is_empty() {
+            return Err(error::AppError::NoChainsConfigured.into());
+        }
+        eprintln!("No chains configured, set CHAIN_RPC_URLS or add one through POST /admin/chains");
     }
 
-    let contract_addresses = env::var("CONTRACT_ADDRESSES").unwrap_or_default();
     for chain in &chains {
-        for addr in contract_addresses.split(',') {
-            let addr = addr.trim();
+        // Entries are `address` or `address@start_block` to backfill from a given block.
+        for entry in contract_addresses.split(',') {
+            let (addr, start_block) = match entry.split_once('@') {
+                Some((addr, start_block)) => {
+                    (addr.trim(), Some(start_block.trim().parse::<u64>()?))
+                }
+                None => (entry.trim(), None),
+            };
             if !addr.is_empty() {
-                EvmSyncLogs::find_or_create_by_address(addr, chain.id as u64, &db_pool).await?;
+                let addr = Address::from_str(addr)
+                    .map_err(|_| error::AppError::InvalidAddress(addr.to_string()))?
+                    .to_string();
+                let sync_log =
+                    EvmSyncLogs::find_or_create_by_address(&addr, chain.id as u64, &db_pool)
+                        .await?;
+                if let Some(start_block) = start_block {
+                    sync_log
+                        .update_start_block_number(start_block, &db_pool)
+                        .await?;
+                }
             }
         }
     }
 
-    let mut service_futures = JoinSet::new();
-
-    for chain in chains {
-        let chain_id = chain.id as u64;
-        let block_time = chain.block_time.unwrap_or(12) as u64;
-
-        let sync_logs = EvmSyncLogs::find_all_by_chain_id(chain_id, &db_pool).await?;
-
-        for sync_log in sync_logs {
-            let mut service = ServiceBuilder::new()
-                .rate_limit(1, Duration::from_secs(block_time))
-                .service(ListenerService {
-                    chain_id,
-                    address: sync_log.contract_address.clone(),
-                    db_pool: db_pool.clone(),
-                    transfer_tx: transfer_tx.clone(),
-                });
-
-            let address = sync_log.contract_address.clone();
-            let future = async move {
-                loop {
-                    if service.ready().await.is_ok() {
-                        match service.call(()).await {
-                            Ok(()) => {}
-                            Err(err) => {
-                                eprintln!("Failed to index {}: {:?}", address, err);
-                                tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                            }
-                        }
-                    }
-                }
+    // Files are named `<address>.json` and hold either a JSON ABI or a list of event
+    // signatures; matching events of those contracts are stored as contract events.
+    if let Ok(abi_dir) = env::var("CONTRACT_ABI_DIR") {
+        for entry in std::fs::read_dir(&abi_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(addr) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
             };
-
-            service_futures.spawn(future);
+            // Checksummed like every stored address, whatever the case of the file name.
+            let addr = Address::from_str(addr)
+                .map_err(|_| error::AppError::InvalidAddress(addr.to_string()))?
+                .to_string();
+            let contents: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let abi = serde_json::to_value(events::parse_abi(&contents)?)?;
+            for chain in &chains {
+                EvmSyncLogs::find_or_create_by_address(&addr, chain.id as u64, &db_pool)
+                    .await?
+                    .update_abi(&abi, &db_pool)
+                    .await?;
+            }
         }
     }
 
-    tokio::select! {
-        _ = server_handle => {},
-        _ = service_futures.join_all() => {},
+    // Chains and contracts added through the admin API are started by the registry too.
+    for chain in &chains {
+        listeners.start(chain).await;
     }
 
+    server_handle.await?;
+
     Ok(())
 }
@@ -0,0 +1,279 @@
+use std::{error::Error, str::FromStr};
+
+use alloy::{
+    primitives::{Address, Bytes},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::request::TransactionRequest,
+    sol_types::SolCall,
+    transports::TransportError,
+};
+use database::entity::{
+    evm_chains::EvmChains,
+    token_metadata::{NewTokenMetadata, TokenMetadata},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::block_range::is_rate_limit_error;
+use crate::error::AppError;
+
+alloy::sol! {
+    #[sol(rpc)]
+    interface IERC20 {
+        function name() external view returns (string);
+        function symbol() external view returns (string);
+        function decimals() external view returns (uint8);
+        function totalSupply() external view returns (uint256);
+        function balanceOf(address owner) external view returns (uint256);
+    }
+
+    interface IERC165 {
+        function supportsInterface(bytes4 interfaceId) external view returns (bool);
+    }
+}
+
+/// Error code geth and most clients use for calls that reverted with data.
+const EXECUTION_REVERTED_CODE: i64 = 3;
+/// Error fragments of calls the contract rejected, e.g. for a method it does not have.
+const REVERT_MESSAGES: &[&str] = &["revert", "invalid opcode"];
+
+/// ERC-165 interface ids of the NFT standards.
+const ERC721_INTERFACE_ID: [u8; 4] = [0x80, 0xac, 0x58, 0xcd];
+const ERC1155_INTERFACE_ID: [u8; 4] = [0xd9, 0xb6, 0x7a, 0x26];
+
+/// Token standard of a tracked contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenStandard {
+    #[default]
+    Erc20,
+    Erc721,
+    Erc1155,
+}
+
+impl TokenStandard {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenStandard::Erc20 => "erc20",
+            TokenStandard::Erc721 => "erc721",
+            TokenStandard::Erc1155 => "erc1155",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "erc20" => Some(TokenStandard::Erc20),
+            "erc721" => Some(TokenStandard::Erc721),
+            "erc1155" => Some(TokenStandard::Erc1155),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a call failed because the contract reverted, rather than because the
+/// provider could not or would not run it.
+fn is_revert_error(err: &TransportError) -> bool {
+    let Some(payload) = err.as_error_resp() else {
+        return false;
+    };
+    if is_rate_limit_error(err) {
+        return false;
+    }
+
+    let message = payload.message.to_lowercase();
+    payload.code == EXECUTION_REVERTED_CODE
+        || REVERT_MESSAGES
+            .iter()
+            .any(|fragment| message.contains(fragment))
+}
+
+/// Runs a view call, returning `None` when the contract rejects it (reverts or the
+/// method does not exist). Throttling, provider and transport failures are reported
+/// as errors, so they are never taken for a missing method.
+async fn call_optional(
+    provider: &impl Provider,
+    contract: Address,
+    input: Vec<u8>,
+) -> Result<Option<Bytes>, Box<dyn Error + Send + Sync>> {
+    let tx = TransactionRequest::default()
+        .to(contract)
+        .input(input.into());
+
+    match provider.call(&tx).await {
+        Ok(result) if result.is_empty() => Ok(None),
+        Ok(result) => Ok(Some(result)),
+        Err(err) if is_revert_error(&err) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Decodes a `name()`/`symbol()` return value, accepting both the standard `string`
+/// and the `bytes32` used by older tokens such as MKR.
+pub fn decode_text(data: &[u8]) -> Option<String> {
+    if data.len() == 32 {
+        let end = data
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(data.len());
+        return String::from_utf8(data[..end].to_vec())
+            .ok()
+            .filter(|text| !text.is_empty());
+    }
+
+    IERC20::symbolCall::abi_decode_returns(data, false)
+        .ok()
+        .map(|decoded| decoded._0)
+}
+
+/// Reads name, symbol, decimals and total supply from the contract, leaving out
+/// whatever it does not implement. Fails if any call could not be answered, so partial
+/// metadata is never cached.
+pub async fn fetch_token_metadata(
+    provider: &impl Provider,
+    contract: Address,
+) -> Result<NewTokenMetadata, Box<dyn Error + Send + Sync>> {
+    let name = call_optional(provider, contract, IERC20::nameCall {}.abi_encode())
+        .await?
+        .and_then(|data| decode_text(&data));
+
+    let symbol = call_optional(provider, contract, IERC20::symbolCall {}.abi_encode())
+        .await?
+        .and_then(|data| decode_text(&data));
+
+    let decimals = call_optional(provider, contract, IERC20::decimalsCall {}.abi_encode())
+        .await?
+        .and_then(|data| IERC20::decimalsCall::abi_decode_returns(&data, false).ok())
+        .map(|decoded| decoded._0);
+
+    let total_supply = call_optional(provider, contract, IERC20::totalSupplyCall {}.abi_encode())
+        .await?
+        .and_then(|data| IERC20::totalSupplyCall::abi_decode_returns(&data, false).ok())
+        .map(|decoded| decoded._0);
+
+    Ok(NewTokenMetadata {
+        name,
+        symbol,
+        decimals,
+        total_supply,
+    })
+}
+
+async fn supports_interface(
+    provider: &impl Provider,
+    contract: Address,
+    interface_id: [u8; 4],
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let call = IERC165::supportsInterfaceCall {
+        interfaceId: interface_id.into(),
+    };
+    Ok(call_optional(provider, contract, call.abi_encode())
+        .await?
+        .and_then(|data| IERC165::supportsInterfaceCall::abi_decode_returns(&data, false).ok())
+        .is_some_and(|decoded| decoded._0))
+}
+
+/// Detects whether a contract is an ERC-721 or ERC-1155 collection through ERC-165,
+/// falling back to ERC-20 for contracts that support neither. Fails when a probe could
+/// not be answered, so the result can be stored.
+pub async fn detect_token_standard(
+    provider: &impl Provider,
+    contract: Address,
+) -> Result<TokenStandard, Box<dyn Error + Send + Sync>> {
+    if supports_interface(provider, contract, ERC721_INTERFACE_ID).await? {
+        return Ok(TokenStandard::Erc721);
+    }
+    if supports_interface(provider, contract, ERC1155_INTERFACE_ID).await? {
+        return Ok(TokenStandard::Erc1155);
+    }
+    Ok(TokenStandard::Erc20)
+}
+
+/// Reads the token's metadata from the contract and stores it, replacing whatever
+/// was cached.
+pub async fn refresh_token_metadata(
+    chain_id: u64,
+    contract_address: &str,
+    db_pool: &Pool<Postgres>,
+) -> Result<TokenMetadata, Box<dyn Error + Send + Sync>> {
+    let chain = EvmChains::fetch_by_id(chain_id, db_pool).await?;
+    let rpc_url = chain
+        .rpc_url
+        .ok_or_else(|| AppError::MissingEnvVar("RPC_URL for chain".into()))?;
+
+    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
+    let contract = Address::from_str(contract_address)?;
+
+    let metadata = fetch_token_metadata(&provider, contract).await?;
+    Ok(TokenMetadata::upsert(chain_id, contract_address, &metadata, db_pool).await?)
+}
+
+/// Returns the cached metadata for a token, reading it from the contract on first use.
+pub async fn get_token_metadata(
+    chain_id: u64,
+    contract_address: &str,
+    db_pool: &Pool<Postgres>,
+) -> Result<TokenMetadata, Box<dyn Error + Send + Sync>> {
+    match TokenMetadata::find_by_address(chain_id, contract_address, db_pool).await? {
+        Some(metadata) => Ok(metadata),
+        None => refresh_token_metadata(chain_id, contract_address, db_pool).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{sol_types::SolValue, transports::TransportErrorKind};
+
+    #[test]
+    fn decodes_string_symbol() {
+        let data = ("USDC".to_string(),).abi_encode_params();
+        assert_eq!(decode_text(&data), Some("USDC".to_string()));
+    }
+
+    #[test]
+    fn decodes_bytes32_symbol() {
+        let mut data = [0u8; 32];
+        data[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_text(&data), Some("MKR".to_string()));
+    }
+
+    #[test]
+    fn token_standard_round_trips() {
+        for standard in [
+            TokenStandard::Erc20,
+            TokenStandard::Erc721,
+            TokenStandard::Erc1155,
+        ] {
+            assert_eq!(TokenStandard::parse(standard.as_str()), Some(standard));
+        }
+        assert_eq!(TokenStandard::parse("erc777"), None);
+    }
+
+    fn error(code: i64, message: &str) -> TransportError {
+        let payload = format!(r#"{{"code":{code},"message":"{message}"}}"#);
+        TransportError::ErrorResp(serde_json::from_str(&payload).unwrap())
+    }
+
+    #[test]
+    fn only_reverts_mean_a_method_is_missing() {
+        assert!(is_revert_error(&error(3, "execution reverted")));
+        assert!(is_revert_error(&error(-32000, "execution reverted")));
+        assert!(is_revert_error(&error(
+            -32015,
+            "VM execution error: invalid opcode"
+        )));
+        assert!(!is_revert_error(&error(-32005, "limit exceeded")));
+        assert!(!is_revert_error(&error(429, "Too Many Requests")));
+        assert!(!is_revert_error(&error(-32000, "header not found")));
+        assert!(!is_revert_error(&TransportErrorKind::http_error(
+            429,
+            String::new()
+        )));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(decode_text(&[0u8; 32]), None);
+        assert_eq!(decode_text(&[1, 2, 3]), None);
+    }
+}
@@ -0,0 +1,194 @@
+use alloy::{
+    primitives::{Address, U256},
+    rpc::types::Log,
+    sol_types::SolEvent,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::erc20::TRANSFER_EVENT_SIGNATURE;
+
+alloy::sol! {
+    interface IERC721 {
+        event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
+    }
+
+    interface IERC1155 {
+        event TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value);
+        event TransferBatch(address indexed operator, address indexed from, address indexed to, uint256[] ids, uint256[] values);
+    }
+}
+
+pub const TRANSFER_SINGLE_EVENT_SIGNATURE: alloy::primitives::B256 =
+    IERC1155::TransferSingle::SIGNATURE_HASH;
+pub const TRANSFER_BATCH_EVENT_SIGNATURE: alloy::primitives::B256 =
+    IERC1155::TransferBatch::SIGNATURE_HASH;
+
+/// One token moved by an ERC-721 `Transfer` or an ERC-1155 `TransferSingle`/`TransferBatch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NftTransfer {
+    /// Account that moved the tokens; only ERC-1155 reports it.
+    pub operator: Option<Address>,
+    pub from: Address,
+    pub to: Address,
+    pub token_id: U256,
+    /// Always 1 for ERC-721.
+    pub amount: U256,
+    /// Position within a `TransferBatch`, 0 otherwise.
+    pub batch_index: u64,
+}
+
+impl NftTransfer {
+    /// Decodes every token moved by `log`, which is empty when it is not an NFT transfer.
+    pub fn from_log(log: &Log) -> Vec<Self> {
+        let Some(&signature) = log.topics().first() else {
+            return Vec::new();
+        };
+
+        // ERC-20 transfers share the signature but keep the amount in the data.
+        if signature == TRANSFER_EVENT_SIGNATURE && log.topics().len() == 4 {
+            return IERC721::Transfer::decode_log_data(log.data(), true)
+                .map(|event| {
+                    vec![NftTransfer {
+                        operator: None,
+                        from: event.from,
+                        to: event.to,
+                        token_id: event.tokenId,
+                        amount: U256::from(1),
+                        batch_index: 0,
+                    }]
+                })
+                .unwrap_or_default();
+        }
+
+        if signature == TRANSFER_SINGLE_EVENT_SIGNATURE {
+            return IERC1155::TransferSingle::decode_log_data(log.data(), true)
+                .map(|event| {
+                    vec![NftTransfer {
+                        operator: Some(event.operator),
+                        from: event.from,
+                        to: event.to,
+                        token_id: event.id,
+                        amount: event.value,
+                        batch_index: 0,
+                    }]
+                })
+                .unwrap_or_default();
+        }
+
+        if signature == TRANSFER_BATCH_EVENT_SIGNATURE {
+            let Ok(event) = IERC1155::TransferBatch::decode_log_data(log.data(), true) else {
+                return Vec::new();
+            };
+            if event.ids.len() != event.values.len() {
+                return Vec::new();
+            }
+
+            return event
+                .ids
+                .iter()
+                .zip(&event.values)
+                .enumerate()
+                .map(|(batch_index, (token_id, amount))| NftTransfer {
+                    operator: Some(event.operator),
+                    from: event.from,
+                    to: event.to,
+                    token_id: *token_id,
+                    amount: *amount,
+                    batch_index: batch_index as u64,
+                })
+                .collect();
+        }
+
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{keccak256, LogData};
+
+    fn log_with(topics: Vec<alloy::primitives::B256>, data: Vec<u8>) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(topics, data.into()),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn event_signatures_match_their_hashes() {
+        assert_eq!(
+            TRANSFER_SINGLE_EVENT_SIGNATURE,
+            keccak256("TransferSingle(address,address,address,uint256,uint256)")
+        );
+        assert_eq!(
+            TRANSFER_BATCH_EVENT_SIGNATURE,
+            keccak256("TransferBatch(address,address,address,uint256[],uint256[])")
+        );
+    }
+
+    #[test]
+    fn decodes_erc721_transfer() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let log = log_with(
+            vec![
+                TRANSFER_EVENT_SIGNATURE,
+                from.into_word(),
+                to.into_word(),
+                U256::from(42).into(),
+            ],
+            vec![],
+        );
+
+        let transfers = NftTransfer::from_log(&log);
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].token_id, U256::from(42));
+        assert_eq!(transfers[0].amount, U256::from(1));
+        assert_eq!((transfers[0].from, transfers[0].to), (from, to));
+        assert!(crate::erc20::Erc20Transfer::from_log(&log).is_none());
+    }
+
+    #[test]
+    fn decodes_erc1155_batch() {
+        let event = IERC1155::TransferBatch {
+            operator: Address::repeat_byte(9),
+            from: Address::ZERO,
+            to: Address::repeat_byte(2),
+            ids: vec![U256::from(1), U256::from(7)],
+            values: vec![U256::from(10), U256::from(3)],
+        };
+        let log = log_with(
+            event
+                .encode_topics()
+                .into_iter()
+                .map(|topic| topic.0)
+                .collect(),
+            event.encode_data(),
+        );
+
+        let transfers = NftTransfer::from_log(&log);
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[1].token_id, U256::from(7));
+        assert_eq!(transfers[1].amount, U256::from(3));
+        assert_eq!(transfers[1].batch_index, 1);
+        assert_eq!(transfers[1].operator, Some(Address::repeat_byte(9)));
+    }
+
+    #[test]
+    fn ignores_erc20_transfers() {
+        let log = log_with(
+            vec![
+                TRANSFER_EVENT_SIGNATURE,
+                Address::repeat_byte(1).into_word(),
+                Address::repeat_byte(2).into_word(),
+            ],
+            U256::from(5).to_be_bytes::<32>().to_vec(),
+        );
+
+        assert!(NftTransfer::from_log(&log).is_empty());
+    }
+}
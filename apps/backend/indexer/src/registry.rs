@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use database::entity::evm_chains::EvmChains;
+use sqlx::{Pool, Postgres};
+use tokio::sync::{broadcast, watch, Mutex as AsyncMutex, OwnedMutexGuard};
+use tokio::task::JoinHandle;
+use tower::{Service, ServiceBuilder, ServiceExt};
+
+use crate::block_range::BlockRange;
+use crate::server::{ContractEventResponse, TransferResponse};
+use crate::service::ListenerService;
+use crate::status::IndexingStatus;
+use crate::subscription;
+
+/// A running chain listener.
+struct Listener {
+    task: JoinHandle<()>,
+    block_range: Arc<Mutex<BlockRange>>,
+}
+
+/// The listener task of every indexed chain, so chains can be started, restarted and
+/// stopped while the indexer runs.
+#[derive(Clone)]
+pub struct ListenerRegistry {
+    db_pool: Pool<Postgres>,
+    transfer_tx: broadcast::Sender<TransferResponse>,
+    event_tx: broadcast::Sender<ContractEventResponse>,
+    listeners: Arc<Mutex<HashMap<u64, Listener>>>,
+    /// Held while a chain's listener is started or stopped, so concurrent admin calls
+    /// never leave two listeners writing to one chain.
+    lifecycles: Arc<Mutex<HashMap<u64, Arc<AsyncMutex<()>>>>>,
+    status: IndexingStatus,
+}
+
+impl ListenerRegistry {
+    pub fn new(
+        db_pool: Pool<Postgres>,
+        transfer_tx: broadcast::Sender<TransferResponse>,
+        event_tx: broadcast::Sender<ContractEventResponse>,
+    ) -> Self {
+        Self {
+            db_pool,
+            transfer_tx,
+            event_tx,
+            listeners: Arc::new(Mutex::new(HashMap::new())),
+            lifecycles: Arc::new(Mutex::new(HashMap::new())),
+            status: IndexingStatus::default(),
+        }
+    }
+
+    /// Starts indexing `chain` with its current settings, replacing its running
+    /// listener if there is one. Work the old listener had not committed is redone.
+    pub async fn start(&self, chain: &EvmChains) {
+        let _lifecycle = self.lock(chain.id as u64).await;
+        self.start_locked(chain).await;
+    }
+
+    async fn start_locked(&self, chain: &EvmChains) {
+        let chain_id = chain.id as u64;
+        let block_time = chain.block_time.unwrap_or(12) as u64;
+
+        let block_range = self
+            .take(chain_id)
+            .await
+            .unwrap_or_else(|| Arc::new(Mutex::new(BlockRange::default())));
+        let new_heads = match &chain.ws_url {
+            Some(ws_url) => subscription::watch_new_heads(chain_id, ws_url.clone()),
+            None => watch::channel(None).1,
+        };
+
+        let mut service = ServiceBuilder::new()
+            .rate_limit(1, Duration::from_secs(block_time))
+            .service(ListenerService {
+                chain_id,
+                db_pool: self.db_pool.clone(),
+                transfer_tx: self.transfer_tx.clone(),
+                event_tx: self.event_tx.clone(),
+                block_range: block_range.clone(),
+                new_heads,
+                status: self.status.clone(),
+            });
+        let status = self.status.clone();
+
+        let task = tokio::spawn(async move {
+            loop {
+                if service.ready().await.is_ok() {
+                    match service.call(()).await {
+                        Ok(()) => {}
+                        Err(err) => {
+                            eprintln!("Failed to index chain {}: {:?}", chain_id, err);
+                            status.record_error(chain_id, err.to_string());
+                            tokio::time::sleep(Duration::from_secs(30)).await;
+                        }
+                    }
+                }
+            }
+        });
+
+        println!("Started listener for chain {chain_id}");
+        self.listeners
+            .lock()
+            .unwrap()
+            .insert(chain_id, Listener { task, block_range });
+    }
+
+    /// Reloads `chain_id` from the database and restarts its listener, e.g. after its
+    /// contracts changed.
+    pub async fn restart(&self, chain_id: u64) -> Result<(), sqlx::Error> {
+        let _lifecycle = self.lock(chain_id).await;
+        let chain = EvmChains::fetch_by_id(chain_id, &self.db_pool).await?;
+        self.start_locked(&chain).await;
+        Ok(())
+    }
+
+    /// Stops the listener of `chain_id`; returns whether one was running.
+    pub async fn stop(&self, chain_id: u64) -> bool {
+        let _lifecycle = self.lock(chain_id).await;
+        let stopped = self.take(chain_id).await.is_some();
+        self.status.remove(chain_id);
+        if stopped {
+            println!("Stopped listener for chain {chain_id}");
+        }
+        stopped
+    }
+
+    /// Whether `chain_id` has a listener that is still running.
+    pub fn is_running(&self, chain_id: u64) -> bool {
+        self.listeners
+            .lock()
+            .unwrap()
+            .get(&chain_id)
+            .is_some_and(|listener| !listener.task.is_finished())
+    }
+
+    /// Progress reported by the listeners.
+    pub fn status(&self) -> &IndexingStatus {
+        &self.status
+    }
+
+    /// Waits until no other start or stop of `chain_id` is in progress.
+    async fn lock(&self, chain_id: u64) -> OwnedMutexGuard<()> {
+        let lifecycle = self
+            .lifecycles
+            .lock()
+            .unwrap()
+            .entry(chain_id)
+            .or_default()
+            .clone();
+        lifecycle.lock_owned().await
+    }
+
+    /// Removes the listener of `chain_id` and waits until it has stopped, so nothing it
+    /// was writing is committed afterwards. Returns its `eth_getLogs` window.
+    async fn take(&self, chain_id: u64) -> Option<Arc<Mutex<BlockRange>>> {
+        let listener = self.listeners.lock().unwrap().remove(&chain_id)?;
+        listener.task.abort();
+        let _ = listener.task.await;
+        Some(listener.block_range)
+    }
+}
@@ -0,0 +1,160 @@
+use std::error::Error;
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    providers::Provider,
+    rpc::types::{BlockTransactionsKind, Header},
+};
+use database::entity::{
+    contract_events::ContractEvents, erc20_approvals::Erc20Approvals,
+    erc20_transfers::Erc20Transfers, evm_blocks::EvmBlocks, evm_sync_logs::EvmSyncLogs,
+    nft_transfers::NftTransfers, token_balances::TokenBalances,
+    token_transfer_rollups::TokenTransferRollups, transfer_rollbacks::TransferRollbacks,
+};
+use sqlx::{Pool, Postgres};
+use tokio::sync::broadcast;
+
+use crate::error::AppError;
+use crate::server::{ContractEventResponse, TransferResponse};
+
+/// How far back we are willing to walk looking for a common ancestor before giving up.
+pub const MAX_REORG_DEPTH: u64 = 128;
+
+pub async fn fetch_header(
+    provider: &impl Provider,
+    block_number: u64,
+) -> Result<Header, Box<dyn Error + Send + Sync>> {
+    let block = provider
+        .get_block_by_number(
+            BlockNumberOrTag::Number(block_number),
+            BlockTransactionsKind::Hashes,
+        )
+        .await?
+        .ok_or(AppError::BlockNotFound(block_number))?;
+
+    Ok(block.header)
+}
+
+/// Compares the parent hash of `header` with the hash we recorded for the block before it.
+///
+/// Returns the common ancestor to roll back to when the chains diverge, or `None` when
+/// the header extends what we have indexed (or we have nothing recorded to compare with).
+pub async fn detect_reorg(
+    provider: &impl Provider,
+    chain_id: u64,
+    header: &Header,
+    db_pool: &Pool<Postgres>,
+) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+    if header.number == 0 {
+        return Ok(None);
+    }
+
+    let Some(parent) = EvmBlocks::find_by_number(chain_id, header.number - 1, db_pool).await?
+    else {
+        return Ok(None);
+    };
+
+    if parent.block_hash == header.parent_hash.as_slice() {
+        return Ok(None);
+    }
+
+    let ancestor = find_common_ancestor(provider, chain_id, header.number - 1, db_pool).await?;
+    Ok(Some(ancestor))
+}
+
+/// Walks back through the recorded blocks from `block_number` until one still matches
+/// the canonical chain.
+pub async fn find_common_ancestor(
+    provider: &impl Provider,
+    chain_id: u64,
+    block_number: u64,
+    db_pool: &Pool<Postgres>,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let lowest = block_number.saturating_sub(MAX_REORG_DEPTH);
+    let mut cursor = block_number;
+
+    loop {
+        let recorded = EvmBlocks::find_before(chain_id, cursor, 32, db_pool).await?;
+        if recorded.is_empty() {
+            // Nothing older to compare against, so the block below the oldest
+            // mismatch is the best ancestor we can offer.
+            return Ok(cursor);
+        }
+
+        for block in recorded {
+            let block_number = block.block_number as u64;
+            if block_number < lowest {
+                return Err(AppError::ReorgTooDeep(MAX_REORG_DEPTH).into());
+            }
+
+            let header = fetch_header(provider, block_number).await?;
+            if header.hash.as_slice() == block.block_hash || block_number == 0 {
+                return Ok(block_number);
+            }
+            cursor = block_number - 1;
+        }
+    }
+}
+
+/// Deletes everything indexed above `ancestor` on `chain_id`, rewinds the sync cursors
+/// and broadcasts the deleted transfers and events with `removed` set.
+pub async fn rollback(
+    chain_id: u64,
+    ancestor: u64,
+    db_pool: &Pool<Postgres>,
+    transfer_tx: &broadcast::Sender<TransferResponse>,
+    event_tx: &broadcast::Sender<ContractEventResponse>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut tx = db_pool.begin().await?;
+
+    let last_transfer_id = Erc20Transfers::max_id_by_chain(chain_id, &mut tx).await?;
+    let removed = Erc20Transfers::delete_after_block(chain_id, ancestor, &mut tx).await?;
+    // Streams resuming from before the rollback cannot replay its removal notices.
+    if let Some(first_removed_id) = removed.iter().map(|transfer| transfer.id).min() {
+        TransferRollbacks::create(
+            chain_id,
+            ancestor,
+            first_removed_id,
+            last_transfer_id,
+            &mut tx,
+        )
+        .await?;
+    }
+    if let Some(since) = removed
+        .iter()
+        .filter_map(|transfer| transfer.block_timestamp)
+        .min()
+    {
+        TokenTransferRollups::rebuild_since(chain_id, since, &mut tx).await?;
+    }
+    Erc20Approvals::delete_after_block(chain_id, ancestor, &mut tx).await?;
+    TokenBalances::delete_after_block(chain_id, ancestor, &mut tx).await?;
+    let removed_nfts = NftTransfers::delete_after_block(chain_id, ancestor, &mut tx).await?;
+    let removed_events = ContractEvents::delete_after_block(chain_id, ancestor, &mut tx).await?;
+    EvmBlocks::delete_after(chain_id, ancestor, &mut tx).await?;
+    EvmSyncLogs::rewind_chain(chain_id, ancestor, &mut tx).await?;
+
+    tx.commit().await?;
+
+    println!(
+        "Reorg on chain {chain_id}: rolled back to block {ancestor}, removed {} transfers",
+        removed.len() + removed_nfts.len()
+    );
+
+    let removed = removed.into_iter().map(TransferResponse::from).chain(
+        removed_nfts
+            .into_iter()
+            .map(TransferResponse::from_nft_transfer),
+    );
+    for mut transfer_response in removed {
+        transfer_response.removed = true;
+        let _ = transfer_tx.send(transfer_response);
+    }
+    for event in removed_events {
+        let mut event_response = ContractEventResponse::from(event);
+        event_response.removed = true;
+        let _ = event_tx.send(event_response);
+    }
+
+    Ok(())
+}
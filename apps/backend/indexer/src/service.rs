@@ -1,35 +1,67 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
     error::Error,
     future::Future,
     pin::Pin,
     str::FromStr,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
 use alloy::{
-    eips::BlockNumberOrTag,
-    primitives::{Address, U256},
+    primitives::{Address, B256},
     providers::{Provider, ProviderBuilder},
-    rpc::types::{request::TransactionRequest, Filter},
-    sol_types::SolCall,
+    rpc::types::{Filter, Log},
 };
 use database::entity::{
-    erc20_transfers::Erc20Transfers, evm_chains::EvmChains, evm_sync_logs::EvmSyncLogs,
+    contract_events::{ContractEvents, NewContractEvent},
+    erc20_approvals::{Erc20Approvals, NewErc20Approval},
+    erc20_transfers::{Erc20Transfers, NewErc20Transfer},
+    evm_blocks::EvmBlocks,
+    evm_chains::EvmChains,
+    evm_sync_logs::EvmSyncLogs,
+    nft_transfers::{NewNftTransfer, NftTransfers},
+    token_balances::TokenBalances,
+    token_metadata::TokenMetadata,
+    token_transfer_rollups::TokenTransferRollups,
 };
-use sqlx::{Pool, Postgres};
-use tokio::sync::broadcast;
-use tokio::time::{sleep, Duration};
+use futures::{stream, StreamExt, TryStreamExt};
+use sqlx::{postgres::PgConnection, types::chrono, Pool, Postgres};
+use tokio::sync::{broadcast, watch};
+use tokio::time::Duration;
 use tower::Service;
 
-use crate::erc20::Erc20Transfer;
+use crate::block_range::{fetch_logs, BlockRange};
+use crate::erc20::{
+    Erc20Approval, Erc20Transfer, APPROVAL_EVENT_SIGNATURE, DEPOSIT_EVENT_SIGNATURE,
+    TRANSFER_EVENT_SIGNATURE, WITHDRAWAL_EVENT_SIGNATURE,
+};
 use crate::error::AppError;
-use crate::server::TransferResponse;
+use crate::events::ContractAbi;
+use crate::metadata::{detect_token_standard, get_token_metadata};
+use crate::nft::{NftTransfer, TRANSFER_BATCH_EVENT_SIGNATURE, TRANSFER_SINGLE_EVENT_SIGNATURE};
+use crate::reorg::{detect_reorg, fetch_header, rollback};
+use crate::server::{ContractEventResponse, TransferResponse};
+use crate::status::IndexingStatus;
+use crate::subscription::wait_for_new_head;
+
+/// Blocks indexed behind the head when a contract has no known start block.
+pub const LIVE_BLOCK_RANGE: u64 = 10;
+
+/// Block headers requested at once when logs land in blocks missing from `evm_blocks`.
+const HEADER_FETCH_CONCURRENCY: usize = 16;
 
+/// Indexes every contract tracked on a chain.
 pub struct ListenerService {
     pub chain_id: u64,
-    pub address: String,
     pub db_pool: Pool<Postgres>,
     pub transfer_tx: broadcast::Sender<TransferResponse>,
+    pub event_tx: broadcast::Sender<ContractEventResponse>,
+    /// `eth_getLogs` window, kept across restarts of the listener.
+    pub block_range: Arc<Mutex<BlockRange>>,
+    /// Heads announced by the chain's `newHeads` subscription, if it has one.
+    pub new_heads: watch::Receiver<Option<u64>>,
+    pub status: IndexingStatus,
 }
 
 impl Service<()> for ListenerService {
@@ -44,186 +76,849 @@ impl Service<()> for ListenerService {
     fn call(&mut self, _: ()) -> Self::Future {
         let db_pool = self.db_pool.clone();
         let chain_id = self.chain_id;
-        let address = self.address.clone();
         let transfer_tx = self.transfer_tx.clone();
+        let event_tx = self.event_tx.clone();
+        let block_range = self.block_range.clone();
+        let new_heads = self.new_heads.clone();
+        let status = self.status.clone();
+
+        Box::pin(async move {
+            fetch_and_save_logs(
+                chain_id,
+                db_pool,
+                transfer_tx,
+                event_tx,
+                block_range,
+                new_heads,
+                status,
+            )
+            .await
+        })
+    }
+}
 
-        Box::pin(async move { fetch_and_save_logs(chain_id, db_pool, address, transfer_tx).await })
+/// Finds the block a contract was deployed in by binary searching `eth_getCode`.
+///
+/// Returns `None` when the address has no code at `latest_block`. Needs an archive node.
+pub async fn find_deployment_block(
+    provider: &impl Provider,
+    contract_address: Address,
+    latest_block: u64,
+) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+    let has_code = |block_number: u64| async move {
+        provider
+            .get_code_at(contract_address)
+            .block_id(block_number.into())
+            .await
+            .map(|code| !code.is_empty())
+    };
+
+    if !has_code(latest_block).await? {
+        return Ok(None);
+    }
+
+    let (mut low, mut high) = (0, latest_block);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if has_code(mid).await? {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
     }
+
+    Ok(Some(low))
 }
 
-alloy::sol! {
-    #[sol(rpc)]
-    interface IERC20 {
-        function symbol() external view returns (string);
-        function decimals() external view returns (uint8);
+/// Whether `contract_address` had no code yet before `start_block`, so indexing from
+/// there sees every transfer. Counts as no when it cannot be told, e.g. without an
+/// archive node.
+async fn starts_before_deployment(
+    provider: &impl Provider,
+    contract_address: Address,
+    start_block: u64,
+) -> bool {
+    if start_block == 0 {
+        return true;
+    }
+    match provider
+        .get_code_at(contract_address)
+        .block_id((start_block - 1).into())
+        .await
+    {
+        Ok(code) => code.is_empty(),
+        Err(err) => {
+            eprintln!(
+                "Could not read code of {contract_address} before block {start_block}: {err}"
+            );
+            false
+        }
     }
 }
 
-pub async fn get_token_symbol(
-    chain_id: u64,
-    contract_address: &str,
+/// Picks the first block to index for a contract that has never been synced: the
+/// configured start block, else the detected deployment block, else the recent head.
+///
+/// Records whether that block is at or before the deployment, i.e. whether the balance
+/// ledger will cover every transfer of the contract.
+async fn resolve_start_block(
+    provider: &impl Provider,
+    sync_log: &EvmSyncLogs,
+    latest_block: u64,
     db_pool: &Pool<Postgres>,
-) -> Result<String, Box<dyn Error + Send + Sync>> {
-    let chain = EvmChains::fetch_by_id(chain_id, db_pool).await?;
-    let rpc_url = chain
-        .rpc_url
-        .ok_or_else(|| AppError::MissingEnvVar("RPC_URL for chain".into()))?;
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let contract_address = Address::from_str(&sync_log.contract_address)?;
+    let (start_block, full_history) = match sync_log.start_block_number {
+        Some(start_block_number) => {
+            let start_block = start_block_number as u64;
+            let full_history =
+                starts_before_deployment(provider, contract_address, start_block).await;
+            (start_block, full_history)
+        }
+        None => match find_deployment_block(provider, contract_address, latest_block).await {
+            Ok(Some(deployment_block)) => {
+                println!(
+                    "Detected deployment block {deployment_block} for {}",
+                    sync_log.contract_address
+                );
+                sync_log
+                    .update_start_block_number(deployment_block, db_pool)
+                    .await?;
+                (deployment_block, true)
+            }
+            Ok(None) => (latest_block.saturating_sub(LIVE_BLOCK_RANGE - 1), false),
+            Err(err) => {
+                eprintln!(
+                    "Could not detect deployment block for {}: {err}",
+                    sync_log.contract_address
+                );
+                (latest_block.saturating_sub(LIVE_BLOCK_RANGE - 1), false)
+            }
+        },
+    };
 
-    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
-    let contract = Address::from_str(contract_address)?;
+    if !full_history {
+        eprintln!(
+            "Indexing {} from block {start_block}, after its deployment: its balances will be partial",
+            sync_log.contract_address
+        );
+    }
+    sync_log.update_full_history(full_history, db_pool).await?;
+    Ok(start_block)
+}
 
-    let symbol_call = IERC20::symbolCall {};
-    let tx = TransactionRequest::default()
-        .to(contract)
-        .input(symbol_call.abi_encode().into());
+/// A block we are about to record, with whatever we know about it.
+struct BlockInfo {
+    block_hash: B256,
+    parent_hash: Option<B256>,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
 
-    let result = provider.call(&tx).await?;
-    let decoded = IERC20::symbolCall::abi_decode_returns(&result, true)?;
-    Ok(decoded._0)
+fn to_datetime(timestamp: u64) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
 }
 
-pub async fn get_token_decimals(
+/// Works out the hash and timestamp of every block containing one of `logs`.
+///
+/// Timestamps come from the `evm_blocks` cache when the hash still matches, then from
+/// the log itself when the provider includes it, and otherwise from the block header.
+/// Missing headers are fetched concurrently rather than one round trip per block.
+async fn resolve_log_blocks(
+    provider: &impl Provider,
     chain_id: u64,
-    contract_address: &str,
+    logs: &[Log],
     db_pool: &Pool<Postgres>,
-) -> Result<u8, Box<dyn Error + Send + Sync>> {
-    let chain = EvmChains::fetch_by_id(chain_id, db_pool).await?;
-    let rpc_url = chain
-        .rpc_url
-        .ok_or_else(|| AppError::MissingEnvVar("RPC_URL for chain".into()))?;
+) -> Result<BTreeMap<u64, BlockInfo>, Box<dyn Error + Send + Sync>> {
+    let mut blocks = BTreeMap::new();
+    for log in logs {
+        if let (Some(block_number), Some(block_hash)) = (log.block_number, log.block_hash) {
+            blocks.entry(block_number).or_insert(BlockInfo {
+                block_hash,
+                parent_hash: None,
+                timestamp: log.block_timestamp.and_then(to_datetime),
+            });
+        }
+    }
 
-    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
-    let contract = Address::from_str(contract_address)?;
+    let (Some(&first), Some(&last)) = (blocks.keys().next(), blocks.keys().next_back()) else {
+        return Ok(blocks);
+    };
 
-    let decimals_call = IERC20::decimalsCall {};
-    let tx = TransactionRequest::default()
-        .to(contract)
-        .input(decimals_call.abi_encode().into());
+    let cached: HashMap<u64, EvmBlocks> = EvmBlocks::find_in_range(chain_id, first, last, db_pool)
+        .await?
+        .into_iter()
+        .map(|block| (block.block_number as u64, block))
+        .collect();
+
+    let mut missing = Vec::new();
+    for (block_number, block) in blocks.iter_mut() {
+        if block.timestamp.is_some() {
+            continue;
+        }
 
-    let result = provider.call(&tx).await?;
-    let decoded = IERC20::decimalsCall::abi_decode_returns(&result, true)?;
-    Ok(decoded._0)
+        match cached.get(block_number) {
+            Some(cached) if cached.block_hash == block.block_hash.as_slice() => {
+                block.timestamp = cached.block_timestamp;
+            }
+            _ => {}
+        }
+
+        if block.timestamp.is_none() {
+            missing.push(*block_number);
+        }
+    }
+
+    let headers: Vec<_> = stream::iter(missing)
+        .map(|block_number| fetch_header(provider, block_number))
+        .buffered(HEADER_FETCH_CONCURRENCY)
+        .try_collect()
+        .await?;
+    for header in headers {
+        if let Some(block) = blocks.get_mut(&header.number) {
+            if header.hash == block.block_hash {
+                block.parent_hash = Some(header.parent_hash);
+                block.timestamp = to_datetime(header.timestamp);
+            }
+        }
+    }
+
+    Ok(blocks)
 }
 
-pub async fn fetch_and_save_logs(
+/// Where a log sits on chain; every field is required before it can be stored.
+struct LogPosition {
+    block_number: u64,
+    block_hash: B256,
+    transaction_hash: B256,
+    transaction_index: u64,
+    log_index: u64,
+}
+
+impl LogPosition {
+    fn from_log(log: &Log) -> Result<Self, std::io::Error> {
+        let missing = |field: &str| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Missing {field}"))
+        };
+
+        Ok(Self {
+            block_number: log.block_number.ok_or_else(|| missing("block number"))?,
+            block_hash: log.block_hash.ok_or_else(|| missing("block hash"))?,
+            transaction_hash: log
+                .transaction_hash
+                .ok_or_else(|| missing("transaction hash"))?,
+            transaction_index: log
+                .transaction_index
+                .ok_or_else(|| missing("transaction index"))?,
+            log_index: log.log_index.ok_or_else(|| missing("log index"))?,
+        })
+    }
+}
+
+/// Debits the sender and credits the recipient of a newly stored transfer in the
+/// balance ledger. The zero address stands for mints and burns and has no balance.
+async fn apply_balance_deltas(
+    chain_id: u64,
+    transfer: &Erc20Transfers,
+    tx: &mut PgConnection,
+) -> Result<(), sqlx::Error> {
+    let deltas = [
+        (&transfer.from_address, -transfer.amount.clone()),
+        (&transfer.to_address, transfer.amount.clone()),
+    ];
+    for (holder, delta) in deltas {
+        if holder.as_slice() == Address::ZERO.as_slice() {
+            continue;
+        }
+        TokenBalances::apply_delta(
+            chain_id,
+            &transfer.contract_address,
+            holder,
+            transfer.block_number as u64,
+            &delta,
+            tx,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Groups contracts by the block they need next, so contracts at similar heights
+/// share one `eth_getLogs` call. A group starts at its lowest block and takes in
+/// every contract within `window` blocks of it.
+pub fn group_by_height<T>(mut contracts: Vec<(u64, T)>, window: u64) -> Vec<(u64, Vec<T>)> {
+    contracts.sort_by_key(|(next_block, _)| *next_block);
+
+    let mut groups: Vec<(u64, Vec<T>)> = Vec::new();
+    for (next_block, contract) in contracts {
+        match groups.last_mut() {
+            Some((start, members)) if next_block < start.saturating_add(window) => {
+                members.push(contract)
+            }
+            _ => groups.push((next_block, vec![contract])),
+        }
+    }
+    groups
+}
+
+/// Indexes every tracked contract on one chain, batching their logs into as few
+/// `eth_getLogs` calls as their sync heights allow.
+struct ChainIndexer {
     chain_id: u64,
     db_pool: Pool<Postgres>,
-    address: String,
     transfer_tx: broadcast::Sender<TransferResponse>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    loop {
-        let chain = EvmChains::fetch_by_id(chain_id, &db_pool).await?;
-        let rpc_url = chain
-            .rpc_url
-            .ok_or_else(|| AppError::MissingEnvVar("RPC_URL for chain".into()))?;
+    event_tx: broadcast::Sender<ContractEventResponse>,
+    block_range: Arc<Mutex<BlockRange>>,
+    /// Event ABIs registered for contracts, re-read every round.
+    abis: HashMap<Address, ContractAbi>,
+    /// Token decimals by contract, once its metadata has been read.
+    decimals: HashMap<Address, Option<u8>>,
+    /// Transfers streamed as pending, by transaction hash and log index, until their
+    /// block is final.
+    pending: HashMap<(B256, u64), TransferResponse>,
+    status: IndexingStatus,
+}
 
-        let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
-        let sync_log = EvmSyncLogs::find_or_create_by_address(&address, chain_id, &db_pool).await?;
+impl ChainIndexer {
+    /// Parses a tracked contract's address, detecting its token standard and loading its
+    /// decimals until both have been read.
+    ///
+    /// Contracts whose standard or metadata cannot be read, e.g. because the provider
+    /// throttled the calls, are still indexed: their standard stays unknown and their
+    /// amounts unformatted, and the failure is reported as their last error. Both are
+    /// read again on the next round.
+    async fn track(
+        &mut self,
+        provider: &impl Provider,
+        sync_log: &EvmSyncLogs,
+    ) -> Result<Address, Box<dyn Error + Send + Sync>> {
+        let contract_address = &sync_log.contract_address;
+        let address = Address::from_str(contract_address)?;
+        match sync_log.abi.as_ref().and_then(ContractAbi::from_stored) {
+            Some(abi) => self.abis.insert(address, abi),
+            None => self.abis.remove(&address),
+        };
+        if sync_log.token_standard.is_none() {
+            match detect_token_standard(provider, address).await {
+                Ok(standard) => {
+                    sync_log
+                        .update_token_standard(standard.as_str(), &self.db_pool)
+                        .await?
+                }
+                Err(err) => self.report(
+                    contract_address,
+                    format!("Failed to detect token standard: {err}"),
+                ),
+            }
+        }
+        if !self.decimals.contains_key(&address) {
+            match get_token_metadata(self.chain_id, contract_address, &self.db_pool).await {
+                Ok(metadata) => {
+                    self.decimals
+                        .insert(address, metadata.decimals.map(|decimals| decimals as u8));
+                }
+                Err(err) => self.report(
+                    contract_address,
+                    format!("Failed to load token metadata: {err}"),
+                ),
+            }
+        }
+        Ok(address)
+    }
 
-        let latest_block = provider.get_block_number().await?;
-        if latest_block == sync_log.last_synced_block_number as u64 {
-            println!("Fully indexed address: {address}, sleeping for 60 seconds");
-            sleep(Duration::from_secs(60)).await;
-            continue;
+    /// Reports a failure that only affects one contract without stopping the chain.
+    fn report(&self, contract_address: &str, message: String) {
+        eprintln!(
+            "Contract {contract_address} on chain {}: {message}",
+            self.chain_id
+        );
+        self.status
+            .record_batch_error(self.chain_id, [contract_address], message);
+    }
+
+    /// Indexes the contracts in `sync_logs` from `from_block_number` onwards.
+    ///
+    /// Returns the last block indexed, or `None` when a reorganization was found and
+    /// rolled back instead.
+    async fn index_batch(
+        &mut self,
+        provider: &impl Provider,
+        from_block_number: u64,
+        latest_block: u64,
+        sync_logs: Vec<(Address, u64, EvmSyncLogs)>,
+    ) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+        let chain_id = self.chain_id;
+
+        let from_header = fetch_header(provider, from_block_number).await?;
+        if let Some(ancestor) =
+            detect_reorg(provider, chain_id, &from_header, &self.db_pool).await?
+        {
+            rollback(
+                chain_id,
+                ancestor,
+                &self.db_pool,
+                &self.transfer_tx,
+                &self.event_tx,
+            )
+            .await?;
+            return Ok(None);
         }
 
-        let from_block_number = match sync_log.last_synced_block_number as u64 {
-            0 => latest_block - 9,
-            block_number => block_number + 1_u64,
-        };
+        let addresses: Vec<Address> = sync_logs.iter().map(|(address, ..)| *address).collect();
+        let mut signatures = vec![
+            TRANSFER_EVENT_SIGNATURE,
+            DEPOSIT_EVENT_SIGNATURE,
+            WITHDRAWAL_EVENT_SIGNATURE,
+            APPROVAL_EVENT_SIGNATURE,
+            TRANSFER_SINGLE_EVENT_SIGNATURE,
+            TRANSFER_BATCH_EVENT_SIGNATURE,
+        ];
+        for abi in addresses
+            .iter()
+            .filter_map(|address| self.abis.get(address))
+        {
+            for selector in abi.selectors() {
+                if !signatures.contains(&selector) {
+                    signatures.push(selector);
+                }
+            }
+        }
+        let filter = Filter::new().address(addresses).event_signature(signatures);
+        let mut range = *self.block_range.lock().unwrap();
+        let (to_block_number, logs) = fetch_logs(
+            provider,
+            &filter,
+            from_block_number,
+            latest_block,
+            &mut range,
+        )
+        .await?;
+        *self.block_range.lock().unwrap() = range;
 
-        let to_block_number = match sync_log.last_synced_block_number as u64 {
-            0 => latest_block,
-            block_number => std::cmp::min(block_number + 10_u64, latest_block), // get the smallest value
-        };
         println!(
-            "Indexing address: {address}, from {} block to {}",
-            from_block_number, to_block_number
+            "Indexing {} contracts on chain {chain_id}, from {} block to {} (window {})",
+            sync_logs.len(),
+            from_block_number,
+            to_block_number,
+            range.size()
         );
-        let filter = Filter::new()
-            .address(Address::from_str(&address)?)
-            .from_block(BlockNumberOrTag::Number(from_block_number))
-            .to_block(BlockNumberOrTag::Number(to_block_number));
+        let to_header = fetch_header(provider, to_block_number).await?;
+        let mut log_blocks = resolve_log_blocks(provider, chain_id, &logs, &self.db_pool).await?;
+        for header in [&from_header, &to_header] {
+            log_blocks.insert(
+                header.number,
+                BlockInfo {
+                    block_hash: header.hash,
+                    parent_hash: Some(header.parent_hash),
+                    timestamp: to_datetime(header.timestamp),
+                },
+            );
+        }
 
-        let logs = provider.get_logs(&filter).await?;
+        let next_blocks: HashMap<Address, u64> = sync_logs
+            .iter()
+            .map(|(address, next_block, _)| (*address, *next_block))
+            .collect();
+
+        let mut tx = self.db_pool.begin().await?;
+
+        // Remember the hash of every block we touched so the next batch can
+        // detect a reorganization by comparing parent hashes.
+        for (block_number, block) in &log_blocks {
+            EvmBlocks::upsert(
+                chain_id,
+                *block_number,
+                block.block_hash.as_slice(),
+                block.parent_hash.as_ref().map(|hash| hash.as_slice()),
+                block.timestamp,
+                &mut tx,
+            )
+            .await?;
+        }
 
-        let mut tx = db_pool.begin().await?;
-        let contract_address = Address::from_str(&address)?;
+        let mut created_transfers = Vec::new();
+        let mut created_nft_transfers = Vec::new();
+        let mut created_events = Vec::new();
+        let mut transfer_counts: HashMap<Address, u64> = HashMap::new();
+        let mut first_seen_blocks: HashMap<String, i64> = HashMap::new();
+        for log in logs {
+            let contract_address = log.address();
+            // Contracts further ahead already have these blocks indexed.
+            let Some(&next_block) = next_blocks.get(&contract_address) else {
+                continue;
+            };
+
+            let transfer = Erc20Transfer::from_log(&log);
+            let approval = match transfer {
+                Some(_) => None,
+                None => Erc20Approval::from_log(&log),
+            };
+            let nft_transfers = match (&transfer, &approval) {
+                (None, None) => NftTransfer::from_log(&log),
+                _ => Vec::new(),
+            };
+            let contract_event = self
+                .abis
+                .get(&contract_address)
+                .and_then(|abi| abi.decode(&log));
+            if transfer.is_none()
+                && approval.is_none()
+                && nft_transfers.is_empty()
+                && contract_event.is_none()
+            {
+                continue;
+            }
 
-        let decimals = get_token_decimals(chain_id, &address, &db_pool)
-            .await
-            .unwrap_or(18);
+            let position = LogPosition::from_log(&log)?;
+            if position.block_number < next_block {
+                continue;
+            }
+            let block_timestamp = log_blocks
+                .get(&position.block_number)
+                .filter(|block| block.block_hash == position.block_hash)
+                .and_then(|block| block.timestamp);
+
+            if let Some(transfer) = transfer {
+                let new_transfer = NewErc20Transfer {
+                    chain_id,
+                    block_number: position.block_number,
+                    block_hash: position.block_hash.0,
+                    transaction_hash: position.transaction_hash.0,
+                    transaction_index: position.transaction_index,
+                    log_index: position.log_index,
+                    from_address: transfer.from.to_vec(),
+                    to_address: transfer.to.to_vec(),
+                    amount: transfer.amount,
+                    contract_address,
+                    block_timestamp,
+                    kind: transfer.kind.as_str().to_string(),
+                };
+
+                // Any failed statement aborts the transaction, so errors fail the whole
+                // batch, which is retried; `None` means the transfer is already stored.
+                if let Some(transfer_record) =
+                    Erc20Transfers::create(&new_transfer, &mut tx).await?
+                {
+                    apply_balance_deltas(chain_id, &transfer_record, &mut tx).await?;
+                    TokenTransferRollups::record(&transfer_record, &mut tx).await?;
+                    let first_seen = first_seen_blocks
+                        .entry(transfer_record.contract_address.clone())
+                        .or_insert(transfer_record.block_number);
+                    *first_seen = (*first_seen).min(transfer_record.block_number);
+                    *transfer_counts.entry(contract_address).or_default() += 1;
+                    created_transfers.push((contract_address, transfer_record));
+                }
+            }
 
-        for log in logs {
-            if let Some(transfer) = Erc20Transfer::from_log(&log) {
-                let block_number = log.block_number.ok_or_else(|| {
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing block number")
-                })?;
+            if let Some(approval) = approval {
+                let new_approval = NewErc20Approval {
+                    chain_id,
+                    block_number: position.block_number,
+                    block_hash: position.block_hash.0,
+                    block_timestamp,
+                    transaction_hash: position.transaction_hash.0,
+                    transaction_index: position.transaction_index,
+                    log_index: position.log_index,
+                    owner_address: approval.owner.to_vec(),
+                    spender_address: approval.spender.to_vec(),
+                    amount: approval.amount,
+                    contract_address,
+                };
 
-                let transaction_hash = log.transaction_hash.ok_or_else(|| {
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing transaction hash")
-                })?;
+                Erc20Approvals::create(&new_approval, &mut tx).await?;
+            }
 
-                let adjusted_amount = transfer.amount / U256::from(10u64.pow(decimals as u32)); // U256::from(6);
-
-                let transfer_record = Erc20Transfers::create(
-                    block_number,
-                    transaction_hash.to_vec().try_into().map_err(|_| {
-                        std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            "Invalid transaction hash",
-                        )
-                    })?,
-                    transfer.from.to_vec(),
-                    transfer.to.to_vec(),
-                    adjusted_amount,
+            if let Some(contract_event) = contract_event {
+                let new_event = NewContractEvent {
+                    chain_id,
                     contract_address,
-                    &mut tx,
+                    block_number: position.block_number,
+                    block_hash: position.block_hash.0,
+                    block_timestamp,
+                    transaction_hash: position.transaction_hash.0,
+                    transaction_index: position.transaction_index,
+                    log_index: position.log_index,
+                    event_name: contract_event.name,
+                    event_signature: contract_event.signature,
+                    params: contract_event.params,
+                };
+
+                if let Some(event_record) = ContractEvents::create(&new_event, &mut tx).await? {
+                    created_events.push(event_record);
+                }
+            }
+
+            for nft_transfer in nft_transfers {
+                let new_transfer = NewNftTransfer {
+                    chain_id,
+                    block_number: position.block_number,
+                    block_hash: position.block_hash.0,
+                    block_timestamp,
+                    transaction_hash: position.transaction_hash.0,
+                    transaction_index: position.transaction_index,
+                    log_index: position.log_index,
+                    batch_index: nft_transfer.batch_index,
+                    operator_address: nft_transfer.operator.map(|operator| operator.to_vec()),
+                    from_address: nft_transfer.from.to_vec(),
+                    to_address: nft_transfer.to.to_vec(),
+                    token_id: nft_transfer.token_id,
+                    amount: nft_transfer.amount,
+                    contract_address,
+                };
+
+                if let Some(transfer_record) = NftTransfers::create(&new_transfer, &mut tx).await? {
+                    let first_seen = first_seen_blocks
+                        .entry(transfer_record.contract_address.clone())
+                        .or_insert(transfer_record.block_number);
+                    *first_seen = (*first_seen).min(transfer_record.block_number);
+                    *transfer_counts.entry(contract_address).or_default() += 1;
+                    created_nft_transfers.push(transfer_record);
+                }
+            }
+        }
+
+        for (contract_address, first_block) in first_seen_blocks {
+            TokenMetadata::update_first_seen_block(
+                chain_id,
+                &contract_address,
+                first_block as u64,
+                &mut tx,
+            )
+            .await?;
+        }
+
+        for (_, _, sync_log) in &sync_logs {
+            // A shrunken window may stop short of where some contracts already are.
+            if to_block_number > sync_log.last_synced_block_number as u64 {
+                sync_log
+                    .update_last_synced_block_number(to_block_number, &mut tx)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+        println!(
+            "Saved logs for chain {chain_id}, blocks: {from_block_number} to {to_block_number}"
+        );
+        self.status.record_batch(
+            chain_id,
+            sync_logs.iter().map(|(address, next_block, sync_log)| {
+                (
+                    sync_log.contract_address.clone(),
+                    (to_block_number + 1).saturating_sub(*next_block),
+                    transfer_counts.get(address).copied().unwrap_or_default(),
                 )
+            }),
+        );
+
+        // Only announce transfers once they are committed.
+        for (contract_address, transfer) in created_transfers {
+            if let Ok(transaction_hash) = B256::try_from(transfer.transaction_hash.as_slice()) {
+                self.pending
+                    .remove(&(transaction_hash, transfer.log_index as u64));
+            }
+            let decimals = self.decimals.get(&contract_address).copied().flatten();
+            let transfer_response = TransferResponse::from_transfer(transfer, decimals);
+            let _ = self.transfer_tx.send(transfer_response);
+        }
+        for transfer in created_nft_transfers {
+            let _ = self
+                .transfer_tx
+                .send(TransferResponse::from_nft_transfer(transfer));
+        }
+        for event in created_events {
+            let _ = self.event_tx.send(ContractEventResponse::from(event));
+        }
+
+        Ok(Some(to_block_number))
+    }
+
+    /// Streams the ERC-20 transfers in the unconfirmed blocks
+    /// `from_block_number..=latest_block` as pending, without storing them.
+    ///
+    /// Transfers streamed earlier that are neither confirmed nor still present were
+    /// reorganized away, and are streamed again with `removed` set.
+    async fn stream_pending(
+        &mut self,
+        provider: &impl Provider,
+        addresses: Vec<Address>,
+        from_block_number: u64,
+        latest_block: u64,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let filter = Filter::new()
+            .address(addresses)
+            .event_signature(vec![
+                TRANSFER_EVENT_SIGNATURE,
+                DEPOSIT_EVENT_SIGNATURE,
+                WITHDRAWAL_EVENT_SIGNATURE,
+            ])
+            .from_block(from_block_number)
+            .to_block(latest_block);
+        let logs = provider.get_logs(&filter).await?;
+
+        let mut seen = HashSet::new();
+        for log in logs {
+            let Some(transfer) = Erc20Transfer::from_log(&log) else {
+                continue;
+            };
+            let (Some(transaction_hash), Some(log_index)) = (log.transaction_hash, log.log_index)
+            else {
+                continue;
+            };
+
+            let key = (transaction_hash, log_index);
+            seen.insert(key);
+            if self.pending.contains_key(&key) {
+                continue;
+            }
+
+            let decimals = self.decimals.get(&log.address()).copied().flatten();
+            let transfer_response =
+                TransferResponse::pending(self.chain_id, &log, &transfer, decimals);
+            self.pending.insert(key, transfer_response.clone());
+            let _ = self.transfer_tx.send(transfer_response);
+        }
+
+        let transfer_tx = &self.transfer_tx;
+        self.pending.retain(|key, transfer_response| {
+            if seen.contains(key) {
+                return true;
+            }
+            let mut transfer_response = transfer_response.clone();
+            transfer_response.removed = true;
+            let _ = transfer_tx.send(transfer_response);
+            false
+        });
+
+        Ok(())
+    }
+}
+
+pub async fn fetch_and_save_logs(
+    chain_id: u64,
+    db_pool: Pool<Postgres>,
+    transfer_tx: broadcast::Sender<TransferResponse>,
+    event_tx: broadcast::Sender<ContractEventResponse>,
+    block_range: Arc<Mutex<BlockRange>>,
+    mut new_heads: watch::Receiver<Option<u64>>,
+    status: IndexingStatus,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut indexer = ChainIndexer {
+        chain_id,
+        db_pool: db_pool.clone(),
+        transfer_tx,
+        event_tx,
+        block_range,
+        abis: HashMap::new(),
+        decimals: HashMap::new(),
+        pending: HashMap::new(),
+        status: status.clone(),
+    };
+
+    loop {
+        let chain = EvmChains::fetch_by_id(chain_id, &db_pool).await?;
+        let rpc_url = chain
+            .rpc_url
+            .ok_or_else(|| AppError::MissingEnvVar("RPC_URL for chain".into()))?;
+
+        let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
+        let latest_block = provider.get_block_number().await?;
+        // Only blocks at or below this one are stored; the rest are streamed as pending.
+        let final_block =
+            latest_block.saturating_sub(chain.confirmations.unwrap_or(0).max(0) as u64);
+        status.record_head(chain_id, latest_block, final_block);
+
+        // Contracts are re-read every round so newly tracked ones are picked up.
+        let mut addresses = Vec::new();
+        let mut behind = Vec::new();
+        for sync_log in EvmSyncLogs::find_all_by_chain_id(chain_id, &db_pool).await? {
+            let address = indexer.track(&provider, &sync_log).await?;
+            addresses.push(address);
+            let next_block = match sync_log.last_synced_block_number as u64 {
+                0 => resolve_start_block(&provider, &sync_log, final_block, &db_pool).await?,
+                block_number => block_number + 1_u64,
+            };
+            if next_block <= final_block {
+                behind.push((next_block, (address, next_block, sync_log)));
+            }
+        }
+
+        let fully_indexed = behind.is_empty();
+        let window = indexer.block_range.lock().unwrap().size();
+        let mut caught_up = true;
+        for (from_block_number, sync_logs) in group_by_height(behind, window) {
+            let contracts: Vec<String> = sync_logs
+                .iter()
+                .map(|(_, _, sync_log)| sync_log.contract_address.clone())
+                .collect();
+            let indexed = indexer
+                .index_batch(&provider, from_block_number, final_block, sync_logs)
                 .await
-                .inspect_err(|error| eprintln!("Error saving ERC-20 transfer {error}"));
-
-                if let Ok(_transfer_record) = transfer_record {
-                    let created_transfers =
-                        Erc20Transfers::find_by_contract_address(&address, 1, &db_pool)
-                            .await
-                            .unwrap_or_default();
-
-                    if let Some(latest_transfer) = created_transfers.first() {
-                        let transfer_response = TransferResponse {
-                            id: latest_transfer.id,
-                            block_number: latest_transfer.block_number,
-                            transaction_hash: hex::encode(&latest_transfer.transaction_hash),
-                            log_index: latest_transfer.log_index,
-                            from_address: hex::encode(&latest_transfer.from_address),
-                            to_address: hex::encode(&latest_transfer.to_address),
-                            amount: (latest_transfer.amount).to_string(),
-                            contract_address: latest_transfer.contract_address.clone(),
-                            created_at: latest_transfer.created_at.map(|dt| dt.to_rfc3339()),
-                        };
-                        if transfer_response.amount == "0" {
-                            continue;
-                        }
-                        let _ = transfer_tx.send(transfer_response);
-                    }
+                .inspect_err(|err| {
+                    status.record_batch_error(
+                        chain_id,
+                        contracts.iter().map(String::as_str),
+                        err.to_string(),
+                    )
+                })?;
+            match indexed {
+                Some(to_block_number) => caught_up &= to_block_number == final_block,
+                None => {
+                    caught_up = false;
+                    break;
                 }
             }
         }
 
-        let _ = sync_log
-            .update_last_synced_block_number(to_block_number, &mut tx)
-            .await
-            .inspect_err(|error| eprintln!("Error updating last_synced_block_number {error}"));
+        // Keep going without a pause until every contract has caught up with the head.
+        if caught_up {
+            if final_block < latest_block && !addresses.is_empty() {
+                indexer
+                    .stream_pending(&provider, addresses, final_block + 1, latest_block)
+                    .await?;
+            }
 
-        match tx.commit().await {
-            Ok(_) => {
-                println!(
-                    "Saved logs for {address}, blocks: {from_block_number} to {to_block_number}",
-                )
+            if fully_indexed && final_block == latest_block {
+                println!("Fully indexed chain {chain_id}, waiting for the next block");
+                wait_for_new_head(&mut new_heads, Duration::from_secs(60)).await;
+            } else {
+                wait_for_new_head(&mut new_heads, Duration::from_secs(10)).await;
             }
-            Err(err) => eprintln!("{err}"),
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contracts_at_the_same_height_share_a_batch() {
+        let groups = group_by_height(vec![(105, "b"), (100, "a"), (100, "c")], 1_000);
+        assert_eq!(groups, vec![(100, vec!["a", "c", "b"])]);
+    }
+
+    #[test]
+    fn backfilling_contracts_get_their_own_batch() {
+        let groups = group_by_height(
+            vec![
+                (20_000_000, "live"),
+                (6_000_000, "backfill"),
+                (20_000_005, "new"),
+            ],
+            1_000,
+        );
+        assert_eq!(
+            groups,
+            vec![
+                (6_000_000, vec!["backfill"]),
+                (20_000_000, vec!["live", "new"]),
+            ]
+        );
+    }
 
-        sleep(Duration::from_secs(10)).await;
+    #[test]
+    fn window_bounds_a_batch() {
+        let groups = group_by_height(vec![(0, 0), (9, 1), (10, 2), (19, 3)], 10);
+        assert_eq!(groups, vec![(0, vec![0, 1]), (10, vec![2, 3])]);
     }
 }
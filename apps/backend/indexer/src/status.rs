@@ -0,0 +1,240 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono;
+
+/// Span over which indexing throughput is averaged.
+pub const THROUGHPUT_WINDOW: Duration = Duration::from_secs(300);
+
+/// What the listener is doing for a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    /// Catching up with blocks that are already final.
+    Backfill,
+    /// Indexed up to the newest final block, following the head.
+    Live,
+    /// The last attempt to index it failed; it is retried.
+    Error,
+}
+
+/// An indexing failure and when it happened.
+#[derive(Debug, Clone)]
+pub struct IndexingError {
+    pub message: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+impl IndexingError {
+    fn now(message: String) -> Self {
+        Self {
+            message,
+            at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Progress of one contract, as last reported by its chain's listener.
+#[derive(Debug, Clone, Default)]
+pub struct ContractProgress {
+    /// Whether the last batch covering the contract failed.
+    pub failing: bool,
+    pub last_error: Option<IndexingError>,
+    /// Blocks and transfers indexed per batch within the [`THROUGHPUT_WINDOW`].
+    batches: VecDeque<(Instant, u64, u64)>,
+}
+
+impl ContractProgress {
+    /// Blocks and transfers indexed per second over the [`THROUGHPUT_WINDOW`].
+    pub fn throughput(&self, now: Instant) -> (f64, f64) {
+        let (blocks, transfers) = self
+            .batches
+            .iter()
+            .filter(|(at, ..)| now.duration_since(*at) <= THROUGHPUT_WINDOW)
+            .fold((0, 0), |(blocks, transfers), (_, b, t)| {
+                (blocks + b, transfers + t)
+            });
+        let window = THROUGHPUT_WINDOW.as_secs_f64();
+        (blocks as f64 / window, transfers as f64 / window)
+    }
+}
+
+/// Progress of one chain, as last reported by its listener.
+#[derive(Debug, Clone, Default)]
+pub struct ChainProgress {
+    pub head_block: Option<u64>,
+    /// Newest block that is final, i.e. deep enough to be stored.
+    pub final_block: Option<u64>,
+    pub head_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether the listener's last round failed.
+    pub failing: bool,
+    pub last_error: Option<IndexingError>,
+    /// By contract address, as stored in `evm_sync_logs`.
+    pub contracts: HashMap<String, ContractProgress>,
+}
+
+impl ChainProgress {
+    /// Where a contract synced up to `last_synced_block` stands.
+    pub fn mode(&self, contract_address: &str, last_synced_block: u64) -> Option<SyncMode> {
+        let failing = self
+            .contracts
+            .get(contract_address)
+            .is_some_and(|contract| contract.failing);
+        if self.failing || failing {
+            return Some(SyncMode::Error);
+        }
+        let final_block = self.final_block?;
+        Some(if last_synced_block >= final_block {
+            SyncMode::Live
+        } else {
+            SyncMode::Backfill
+        })
+    }
+}
+
+/// Progress reported by the chain listeners, shared with `/status`. Only kept in
+/// memory; the sync cursors themselves live in `evm_sync_logs`.
+#[derive(Clone, Default)]
+pub struct IndexingStatus {
+    chains: Arc<Mutex<HashMap<u64, ChainProgress>>>,
+}
+
+impl IndexingStatus {
+    pub fn chain(&self, chain_id: u64) -> Option<ChainProgress> {
+        self.chains.lock().unwrap().get(&chain_id).cloned()
+    }
+
+    /// Records the head seen at the start of a round.
+    pub fn record_head(&self, chain_id: u64, head_block: u64, final_block: u64) {
+        let mut chains = self.chains.lock().unwrap();
+        let chain = chains.entry(chain_id).or_default();
+        chain.head_block = Some(head_block);
+        chain.final_block = Some(final_block);
+        chain.head_updated_at = Some(chrono::Utc::now());
+        chain.failing = false;
+    }
+
+    /// Records a committed batch: the blocks each contract advanced and the transfers
+    /// stored for it.
+    pub fn record_batch(
+        &self,
+        chain_id: u64,
+        progress: impl IntoIterator<Item = (String, u64, u64)>,
+    ) {
+        let now = Instant::now();
+        let mut chains = self.chains.lock().unwrap();
+        let chain = chains.entry(chain_id).or_default();
+        for (contract_address, blocks, transfers) in progress {
+            let contract = chain.contracts.entry(contract_address).or_default();
+            contract.failing = false;
+            contract.batches.push_back((now, blocks, transfers));
+            while contract
+                .batches
+                .front()
+                .is_some_and(|(at, ..)| now.duration_since(*at) > THROUGHPUT_WINDOW)
+            {
+                contract.batches.pop_front();
+            }
+        }
+    }
+
+    /// Records a batch that failed for `contract_addresses`.
+    pub fn record_batch_error<'a>(
+        &self,
+        chain_id: u64,
+        contract_addresses: impl IntoIterator<Item = &'a str>,
+        message: String,
+    ) {
+        let error = IndexingError::now(message);
+        let mut chains = self.chains.lock().unwrap();
+        let chain = chains.entry(chain_id).or_default();
+        for contract_address in contract_addresses {
+            let contract = chain
+                .contracts
+                .entry(contract_address.to_string())
+                .or_default();
+            contract.failing = true;
+            contract.last_error = Some(error.clone());
+        }
+    }
+
+    /// Records a failed round of the listener, e.g. when the RPC node is unreachable.
+    pub fn record_error(&self, chain_id: u64, message: String) {
+        let mut chains = self.chains.lock().unwrap();
+        let chain = chains.entry(chain_id).or_default();
+        chain.failing = true;
+        chain.last_error = Some(IndexingError::now(message));
+    }
+
+    /// Forgets a chain whose listener was stopped.
+    pub fn remove(&self, chain_id: u64) {
+        self.chains.lock().unwrap().remove(&chain_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+
+    #[test]
+    fn mode_follows_the_final_block() {
+        let status = IndexingStatus::default();
+        assert!(status.chain(1).is_none());
+
+        status.record_head(1, 110, 100);
+        let chain = status.chain(1).unwrap();
+        assert_eq!(chain.mode(TOKEN, 90), Some(SyncMode::Backfill));
+        assert_eq!(chain.mode(TOKEN, 100), Some(SyncMode::Live));
+    }
+
+    #[test]
+    fn errors_last_until_the_next_success() {
+        let status = IndexingStatus::default();
+        status.record_head(1, 110, 100);
+
+        status.record_batch_error(1, [TOKEN], "timeout".to_string());
+        let chain = status.chain(1).unwrap();
+        assert_eq!(chain.mode(TOKEN, 100), Some(SyncMode::Error));
+        assert_eq!(chain.mode("0x01", 100), Some(SyncMode::Live));
+
+        status.record_batch(1, [(TOKEN.to_string(), 10, 2)]);
+        let chain = status.chain(1).unwrap();
+        assert_eq!(chain.mode(TOKEN, 100), Some(SyncMode::Live));
+        assert_eq!(
+            chain.contracts[TOKEN].last_error.as_ref().unwrap().message,
+            "timeout"
+        );
+
+        status.record_error(1, "connection refused".to_string());
+        assert_eq!(
+            status.chain(1).unwrap().mode(TOKEN, 100),
+            Some(SyncMode::Error)
+        );
+        status.record_head(1, 111, 101);
+        assert_eq!(
+            status.chain(1).unwrap().mode(TOKEN, 100),
+            Some(SyncMode::Backfill)
+        );
+    }
+
+    #[test]
+    fn throughput_is_averaged_over_the_window() {
+        let status = IndexingStatus::default();
+        status.record_batch(1, [(TOKEN.to_string(), 600, 30)]);
+        status.record_batch(1, [(TOKEN.to_string(), 300, 0)]);
+
+        let chain = status.chain(1).unwrap();
+        let now = Instant::now();
+        assert_eq!(chain.contracts[TOKEN].throughput(now), (3.0, 0.1));
+        assert_eq!(
+            chain.contracts[TOKEN].throughput(now + THROUGHPUT_WINDOW * 2),
+            (0.0, 0.0)
+        );
+    }
+}
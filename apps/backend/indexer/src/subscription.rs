@@ -0,0 +1,109 @@
+use std::error::Error;
+
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use futures::StreamExt;
+use tokio::sync::watch;
+use tokio::time::{sleep, Duration};
+
+/// First delay before reconnecting a dropped subscription, doubled on every failure.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Subscribes to `newHeads` on `ws_url` and publishes every head number on the
+/// returned channel, reconnecting with backoff whenever the socket drops.
+///
+/// Listeners keep polling while the subscription is down, and index from their
+/// stored cursor once it is back, so no blocks are skipped across a reconnect.
+pub fn watch_new_heads(chain_id: u64, ws_url: String) -> watch::Receiver<Option<u64>> {
+    let (head_tx, head_rx) = watch::channel(None);
+
+    tokio::spawn(async move {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            match subscribe_new_heads(&ws_url, &head_tx, &mut delay).await {
+                Ok(()) => eprintln!("newHeads subscription on chain {chain_id} closed"),
+                Err(err) => eprintln!("newHeads subscription on chain {chain_id} failed: {err}"),
+            }
+
+            if head_tx.is_closed() {
+                return;
+            }
+
+            println!("Polling chain {chain_id}, reconnecting in {delay:?}");
+            sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+
+    head_rx
+}
+
+async fn subscribe_new_heads(
+    ws_url: &str,
+    head_tx: &watch::Sender<Option<u64>>,
+    delay: &mut Duration,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let provider = ProviderBuilder::new().on_ws(WsConnect::new(ws_url)).await?;
+    let mut heads = provider.subscribe_blocks().await?.into_stream();
+
+    loop {
+        tokio::select! {
+            header = heads.next() => match header {
+                Some(header) => {
+                    *delay = RECONNECT_DELAY;
+                    head_tx.send_replace(Some(header.number));
+                }
+                None => return Ok(()),
+            },
+            // The listener was stopped, so nobody is waiting for heads any more.
+            _ = head_tx.closed() => return Ok(()),
+        }
+    }
+}
+
+/// Waits until a new head is announced, or `poll_interval` has passed when there is
+/// no live subscription (or it is currently down).
+pub async fn wait_for_new_head(
+    new_heads: &mut watch::Receiver<Option<u64>>,
+    poll_interval: Duration,
+) {
+    tokio::select! {
+        changed = new_heads.changed() => {
+            // The subscription task is gone for good, so only polling is left.
+            if changed.is_err() {
+                sleep(poll_interval).await;
+            }
+        }
+        _ = sleep(poll_interval) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{timeout, Instant};
+
+    #[tokio::test]
+    async fn wakes_up_on_new_head() {
+        let (head_tx, mut head_rx) = watch::channel(None);
+        head_tx.send_replace(Some(1));
+
+        timeout(
+            Duration::from_secs(1),
+            wait_for_new_head(&mut head_rx, Duration::from_secs(60)),
+        )
+        .await
+        .expect("should not wait for the poll interval");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_polling_without_subscription() {
+        let (head_tx, mut head_rx) = watch::channel::<Option<u64>>(None);
+        drop(head_tx);
+
+        let started = Instant::now();
+        wait_for_new_head(&mut head_rx, Duration::from_millis(20)).await;
+
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+}